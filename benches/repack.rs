@@ -0,0 +1,80 @@
+//! Сравнение repack с прежней побитовой реализацией.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use num::Integer;
+use std::convert::TryFrom;
+use std::ops::{BitAnd, BitOrAssign, Shl, Shr};
+
+// Реализация repack до перехода на перенос групп бит (один бит за итерацию).
+fn repack_bitwise<T1, T2>(src: &[T1], bits_in: usize, bits_out: usize, bits_limit: usize) -> Vec<T2>
+where
+    T1: BitAnd<Output = T1> + Integer + Clone + Shr<Output = T1> + TryFrom<usize>,
+    T2: Integer + Clone + TryFrom<T1> + BitOrAssign + TryFrom<usize> + Shl<Output = T2>,
+{
+    let mut dst = vec![T2::zero(); bits_limit / bits_out];
+    for i in 0..bits_limit {
+        let src_i = i / bits_in;
+        let src_b = i % bits_in;
+        let dst_i = i / bits_out;
+        let dst_b = i % bits_out;
+
+        let rsh = T1::try_from(bits_in - src_b - 1).ok().unwrap();
+        let lsh = T2::try_from(bits_out - dst_b - 1).ok().unwrap();
+
+        let src_byte = if src_i < src.len() {
+            src[src_i].clone()
+        } else {
+            T1::zero()
+        };
+
+        let src_bit = T2::try_from((src_byte >> rsh) & T1::one()).ok().unwrap();
+        dst[dst_i] |= src_bit << lsh;
+    }
+    dst
+}
+
+fn bench_pair<T1, T2>(c: &mut Criterion, name: &str, src: &[T1], bits_in: usize, bits_out: usize)
+where
+    T1: bits_rs::Word + BitAnd<Output = T1> + Integer + Clone + Shr<Output = T1> + TryFrom<usize>,
+    T2: bits_rs::Word + Integer + Clone + TryFrom<T1> + BitOrAssign + TryFrom<usize> + Shl<Output = T2>,
+{
+    let bits_limit = src.len() * bits_in / bits_out * bits_out;
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes((bits_limit / 8) as u64));
+    group.bench_function(BenchmarkId::new("bitwise", bits_limit), |b| {
+        b.iter(|| repack_bitwise::<T1, T2>(black_box(src), bits_in, bits_out, bits_limit))
+    });
+    group.bench_function(BenchmarkId::new("repack", bits_limit), |b| {
+        b.iter(|| bits_rs::repack::<T1, T2>(black_box(src), bits_in, bits_out, bits_limit).unwrap())
+    });
+    let plan = bits_rs::RepackPlan::<T1, T2>::new(bits_in, bits_out, bits_limit, bits_rs::RepackOptions::new()).unwrap();
+    group.bench_function(BenchmarkId::new("plan", bits_limit), |b| b.iter(|| plan.apply(black_box(src)).unwrap()));
+    // Без выделения памяти, чтобы сравнить сам перенос бит с таблицей сдвигов плана и без неё.
+    let mut dst = vec![T2::zero(); bits_limit / bits_out];
+    group.bench_function(BenchmarkId::new("repack_into", bits_limit), |b| {
+        b.iter(|| bits_rs::repack_into(black_box(src), bits_in, &mut dst, bits_out, bits_limit).unwrap())
+    });
+    group.bench_function(BenchmarkId::new("plan_into", bits_limit), |b| {
+        b.iter(|| plan.apply_into(black_box(src), &mut dst).unwrap())
+    });
+    group.finish();
+}
+
+fn bench_repack(c: &mut Criterion) {
+    const N: usize = 1 << 14;
+    let bytes: Vec<u8> = (0..N).map(|i| (i * 31 + 7) as u8).collect();
+    let words: Vec<u16> = (0..N).map(|i| (i * 2654435761usize) as u16).collect();
+    let words32: Vec<u32> = (0..N).map(|i| (i * 2654435761usize) as u32).collect();
+
+    bench_pair::<u8, u16>(c, "8->16", &bytes, 8, 16);
+    bench_pair::<u16, u8>(c, "16->8", &words, 16, 8);
+    bench_pair::<u16, u8>(c, "12->8", &words, 12, 8);
+    bench_pair::<u16, u8>(c, "10->8", &words, 10, 8);
+    bench_pair::<u8, u16>(c, "8->12", &bytes, 8, 12);
+    bench_pair::<u8, u8>(c, "8->3", &bytes, 8, 3);
+    bench_pair::<u32, u64>(c, "32->64", &words32, 32, 64);
+    bench_pair::<u16, u8>(c, "13->7", &words, 13, 7);
+}
+
+criterion_group!(benches, bench_repack);
+criterion_main!(benches);
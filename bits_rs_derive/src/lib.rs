@@ -0,0 +1,339 @@
+//! Макрос `#[derive(BitPack)]` для крейта `bits_rs`.
+//!
+//! Подключается через feature `derive` крейта `bits_rs`, который
+//! реэкспортирует макрос вместе с одноименным трейтом.
+
+#![deny(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DataEnum, DeriveInput, Error, Fields, Ident, LitInt, LitStr, Member, Result};
+
+/// Выводит `bits_rs::BitPack` для структуры или перечисления и добавляет
+/// типу методы `pack(&self) -> [u8; N]`,
+/// `try_pack(&self) -> Result<[u8; N], bits_rs::PackError>` и
+/// `unpack(&[u8]) -> Result<Self, bits_rs::UnpackError>`.
+///
+/// Поля структуры идут подряд в порядке объявления. Поле с атрибутом
+/// `#[bits(n)]` должно быть целым типом и занимает n младших бит значения
+/// (знаковые значения при распаковке расширяются по знаку). Значение, не
+/// помещающееся в n бит, `pack` молча усекает до младших n бит, а `try_pack`
+/// отклоняет с `PackError::ValueOutOfRange`. Поле без атрибута занимает
+/// `<T as BitPack>::BITS` бит: так вкладываются `bool`, целые типы и другие
+/// типы с `#[derive(BitPack)]`.
+///
+/// Перечисление может содержать только варианты без полей и должно иметь
+/// атрибут `#[bits(n)]` - ширину дискриминанта. Дискриминанты, не
+/// помещающиеся в n бит без знака, отклоняются при компиляции, а неизвестное
+/// значение при распаковке дает `UnpackError::InvalidDiscriminant`.
+///
+/// Атрибут `#[bitpack(...)]` задает хранение:
+/// * `order = Msb0 | Lsb0` - первое поле занимает старшие (Msb0, по
+///   умолчанию) или младшие (Lsb0) биты значения;
+/// * `byte_order = Big | Little | Native` - порядок байтов результата `pack`
+///   (по умолчанию Big);
+/// * `bytes = N` - размер результата `pack` в байтах (по умолчанию
+///   наименьший, вмещающий все биты). Раскладка, не помещающаяся в N байтов,
+///   отклоняется при компиляции.
+///
+/// При Msb0 значение выравнивается по старшим битам хранилища, при Lsb0 - по
+/// младшим. Порядок байтов вложенного типа определяется внешним, а порядок
+/// его полей - собственным атрибутом `order`.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::BitPack;
+///
+///     #[derive(BitPack, Debug, PartialEq)]
+///     #[bits(2)]
+///     enum Kind {
+///         Data,
+///         Ack,
+///         Nak = 3,
+///     }
+///
+///     #[derive(BitPack, Debug, PartialEq)]
+///     struct Header {
+///         #[bits(3)]
+///         version: u8,
+///         kind: Kind,
+///         urgent: bool,
+///         #[bits(10)]
+///         len: u16,
+///     }
+///
+///     // 101 11 1 0101010101
+///     let header = Header { version: 5, kind: Kind::Nak, urgent: true, len: 0x155 };
+///     assert_eq!(header.pack(), [0xBD, 0x55]);
+///     assert_eq!(header.try_pack(), Ok([0xBD, 0x55]));
+///     assert_eq!(Header::unpack(&[0xBD, 0x55]), Ok(header));
+///
+///     #[derive(BitPack, Debug, PartialEq)]
+///     #[bitpack(order = Lsb0, byte_order = Little, bytes = 4)]
+///     struct Reg {
+///         #[bits(4)]
+///         mode: u8,
+///         #[bits(12)]
+///         offset: i16,
+///         header: Header,
+///     }
+///
+///     let header = Header { version: 5, kind: Kind::Nak, urgent: true, len: 0x155 };
+///     let reg = Reg { mode: 0xA, offset: -2, header };
+///     assert_eq!(reg.pack(), [0xEA, 0xFF, 0x55, 0xBD]);
+///     assert_eq!(Reg::unpack(&reg.pack()), Ok(reg));
+///
+///     // 0x12 не помещается в 4 бита поля mode.
+///     let header = Header { version: 5, kind: Kind::Nak, urgent: true, len: 0x155 };
+///     let reg = Reg { mode: 0x12, offset: -2, header };
+///     assert_eq!(reg.pack(), [0xE2, 0xFF, 0x55, 0xBD]);
+///     assert_eq!(reg.try_pack(), Err(bits_rs::PackError::ValueOutOfRange { field: "mode" }));
+/// ```
+///
+/// ```compile_fail
+///     #[derive(bits_rs::BitPack)]
+///     #[bitpack(bytes = 1)]
+///     struct TooWide {
+///         #[bits(5)]
+///         a: u8,
+///         #[bits(4)]
+///         b: u8,
+///     }
+/// ```
+#[proc_macro_derive(BitPack, attributes(bits, bitpack))]
+pub fn derive_bit_pack(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// Параметры атрибута `#[bitpack(...)]`.
+struct Storage {
+    lsb0: bool,
+    byte_order: Ident,
+    bytes: Option<usize>,
+}
+
+fn storage(attrs: &[Attribute]) -> Result<Storage> {
+    let mut storage = Storage {
+        lsb0: false,
+        byte_order: Ident::new("Big", Span::call_site()),
+        bytes: None,
+    };
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("bitpack")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("order") {
+                let order: Ident = meta.value()?.parse()?;
+                storage.lsb0 = match order.to_string().as_str() {
+                    "Msb0" => false,
+                    "Lsb0" => true,
+                    _ => return Err(Error::new(order.span(), "expected `Msb0` or `Lsb0`")),
+                };
+            } else if meta.path.is_ident("byte_order") {
+                let order: Ident = meta.value()?.parse()?;
+                if !matches!(order.to_string().as_str(), "Big" | "Little" | "Native") {
+                    return Err(Error::new(order.span(), "expected `Big`, `Little` or `Native`"));
+                }
+                storage.byte_order = order;
+            } else if meta.path.is_ident("bytes") {
+                let bytes: LitInt = meta.value()?.parse()?;
+                storage.bytes = Some(bytes.base10_parse()?);
+            } else {
+                return Err(meta.error("expected `order`, `byte_order` or `bytes`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(storage)
+}
+
+/// Значение атрибута `#[bits(n)]`, если он есть.
+fn bits(attrs: &[Attribute]) -> Result<Option<usize>> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("bits")) else {
+        return Ok(None);
+    };
+    let lit: LitInt = attr.parse_args()?;
+    let bits: usize = lit.base10_parse()?;
+    if bits == 0 || bits > 128 {
+        return Err(Error::new(lit.span(), "bit width must be in 1..=128"));
+    }
+    Ok(Some(bits))
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(input.generics.span(), "BitPack cannot be derived for generic types"));
+    }
+    let name = &input.ident;
+    let storage = storage(&input.attrs)?;
+    let (bits, write, read, checks, fits) = match &input.data {
+        Data::Struct(data) => expand_struct(name, &data.fields, storage.lsb0)?,
+        Data::Enum(data) => expand_enum(input, data)?,
+        Data::Union(_) => return Err(Error::new(name.span(), "BitPack cannot be derived for unions")),
+    };
+
+    let total = quote!(<#name as ::bits_rs::BitPack>::BITS);
+    let len = match storage.bytes {
+        Some(bytes) => quote!(#bytes),
+        None => quote!(#total.div_ceil(8)),
+    };
+    let base = if storage.lsb0 { quote!(0) } else { quote!(#len * 8 - #total) };
+    let byte_order = &storage.byte_order;
+    let message = LitStr::new(&format!("`{}` does not fit in the declared storage", name), name.span());
+
+    Ok(quote! {
+        impl ::bits_rs::BitPack for #name {
+            const BITS: usize = #bits;
+
+            #[allow(unused_variables)]
+            fn write_bits(&self, bytes: &mut [u8], order: ::bits_rs::ByteOrder, lo: usize) {
+                #write
+            }
+
+            fn check_fits(&self) -> ::core::result::Result<(), ::bits_rs::PackError> {
+                #fits
+            }
+
+            #[allow(unused_variables)]
+            fn read_bits(bytes: &[u8], order: ::bits_rs::ByteOrder, lo: usize) -> ::core::result::Result<Self, ::bits_rs::UnpackError> {
+                #read
+            }
+        }
+
+        const _: () = {
+            assert!(#total <= #len * 8, #message);
+            #checks
+        };
+
+        impl #name {
+            /// Упаковывает значение в байты (см. `bits_rs::BitPack`).
+            pub fn pack(&self) -> [u8; #len] {
+                let mut bytes = [0u8; #len];
+                ::bits_rs::BitPack::write_bits(self, &mut bytes, ::bits_rs::ByteOrder::#byte_order, #base);
+                bytes
+            }
+
+            /// Упаковывает значение в байты, если значения всех полей помещаются
+            /// в свои ширины (см. `bits_rs::BitPack::check_fits`).
+            pub fn try_pack(&self) -> ::core::result::Result<[u8; #len], ::bits_rs::PackError> {
+                ::bits_rs::BitPack::check_fits(self)?;
+                ::core::result::Result::Ok(self.pack())
+            }
+
+            /// Распаковывает значение из первых байтов среза (см. `bits_rs::BitPack`).
+            pub fn unpack(bytes: &[u8]) -> ::core::result::Result<Self, ::bits_rs::UnpackError> {
+                if bytes.len() < #len {
+                    return ::core::result::Result::Err(::bits_rs::UnpackError::SrcTooShort { len: bytes.len(), required: #len });
+                }
+                <Self as ::bits_rs::BitPack>::read_bits(&bytes[..#len], ::bits_rs::ByteOrder::#byte_order, #base)
+            }
+        }
+    })
+}
+
+/// Ширина, запись, чтение, проверки полей структуры и проверка значений полей.
+fn expand_struct(name: &Ident, fields: &Fields, lsb0: bool) -> Result<(TokenStream2, TokenStream2, TokenStream2, TokenStream2, TokenStream2)> {
+    let mut offset = quote!(0usize);
+    let (mut write, mut values, mut checks, mut fits) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for (index, field) in fields.iter().enumerate() {
+        let ty = &field.ty;
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(index.into()),
+        };
+        let var = format_ident!("field{}", index);
+        let declared = bits(&field.attrs)?;
+        let width = match declared {
+            Some(bits) => {
+                let message = LitStr::new(&format!("bit width of field `{}` exceeds its type", quote!(#member)), ty.span());
+                checks.push(quote_spanned!(ty.span()=> assert!(#bits <= <#ty as ::bits_rs::Word>::BITS, #message);));
+                quote!(#bits)
+            }
+            None => quote!(<#ty as ::bits_rs::BitPack>::BITS),
+        };
+        let at = if lsb0 {
+            quote!(lo + (#offset))
+        } else {
+            quote!(lo + <#name as ::bits_rs::BitPack>::BITS - (#offset) - (#width))
+        };
+        if declared.is_some() {
+            write.push(quote!(::bits_rs::pack_word::<#ty>(bytes, order, #at, #width, self.#member);));
+            values.push(quote!(let #var = ::bits_rs::unpack_word::<#ty>(bytes, order, #at, #width);));
+            let field = LitStr::new(&quote!(#member).to_string(), ty.span());
+            fits.push(quote! {
+                if !::bits_rs::word_fits::<#ty>(self.#member, #width) {
+                    return ::core::result::Result::Err(::bits_rs::PackError::ValueOutOfRange { field: #field });
+                }
+            });
+        } else {
+            write.push(quote!(::bits_rs::BitPack::write_bits(&self.#member, bytes, order, #at);));
+            values.push(quote!(let #var = <#ty as ::bits_rs::BitPack>::read_bits(bytes, order, #at)?;));
+            fits.push(quote!(::bits_rs::BitPack::check_fits(&self.#member)?;));
+        }
+        offset = quote!(#offset + #width);
+    }
+
+    let vars = (0..fields.len()).map(|index| format_ident!("field{}", index));
+    let value = match fields {
+        Fields::Named(named) => {
+            let idents = named.named.iter().map(|field| &field.ident);
+            quote!(Self { #(#idents: #vars),* })
+        }
+        Fields::Unnamed(_) => quote!(Self(#(#vars),*)),
+        Fields::Unit => quote!(Self),
+    };
+    let read = quote! {
+        #(#values)*
+        ::core::result::Result::Ok(#value)
+    };
+    let fits = quote! {
+        #(#fits)*
+        ::core::result::Result::Ok(())
+    };
+    Ok((offset, quote!(#(#write)*), read, quote!(#(#checks)*), fits))
+}
+
+/// Ширина, запись, чтение, проверки дискриминанта перечисления и проверка значения.
+fn expand_enum(input: &DeriveInput, data: &DataEnum) -> Result<(TokenStream2, TokenStream2, TokenStream2, TokenStream2, TokenStream2)> {
+    let name = &input.ident;
+    let Some(bits) = bits(&input.attrs)? else {
+        return Err(Error::new(name.span(), "enum requires `#[bits(n)]` discriminant width"));
+    };
+    if data.variants.is_empty() {
+        return Err(Error::new(name.span(), "BitPack cannot be derived for enums without variants"));
+    }
+    let mut checks = Vec::new();
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new(variant.span(), "BitPack enum variants cannot have fields"));
+        }
+        let ident = &variant.ident;
+        let message = LitStr::new(&format!("discriminant of `{}` does not fit in {} bits", ident, bits), ident.span());
+        let check = if bits >= 127 {
+            quote!((#name::#ident as i128) >= 0)
+        } else {
+            quote!((#name::#ident as i128) >= 0 && (#name::#ident as i128) < (1i128 << #bits))
+        };
+        checks.push(quote_spanned!(ident.span()=> assert!(#check, #message);));
+    }
+
+    let idents: Vec<_> = data.variants.iter().map(|variant| &variant.ident).collect();
+    let write = quote! {
+        let value = match self {
+            #(Self::#idents => Self::#idents as i128 as u128,)*
+        };
+        ::bits_rs::pack_word(bytes, order, lo, #bits, value);
+    };
+    let read = quote! {
+        let value = ::bits_rs::unpack_word::<u128>(bytes, order, lo, #bits);
+        #(
+            if value == Self::#idents as i128 as u128 {
+                return ::core::result::Result::Ok(Self::#idents);
+            }
+        )*
+        ::core::result::Result::Err(::bits_rs::UnpackError::InvalidDiscriminant { value })
+    };
+    Ok((quote!(#bits), write, read, quote!(#(#checks)*), quote!(::core::result::Result::Ok(()))))
+}
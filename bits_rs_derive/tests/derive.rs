@@ -0,0 +1,94 @@
+use bits_rs::{BitPack, ByteOrder, PackError, UnpackError};
+
+#[derive(BitPack, Debug, Clone, Copy, PartialEq)]
+#[bits(3)]
+enum Mode {
+    Idle = 1,
+    Run,
+    Stop = 6,
+}
+
+#[derive(BitPack, Debug, Clone, Copy, PartialEq)]
+struct Inner {
+    #[bits(5)]
+    a: u8,
+    #[bits(4)]
+    b: i8,
+}
+
+#[derive(BitPack, Debug, Clone, Copy, PartialEq)]
+#[bitpack(bytes = 4)]
+struct Outer {
+    flag: bool,
+    mode: Mode,
+    inner: Inner,
+    #[bits(11)]
+    c: u16,
+}
+
+#[derive(BitPack, Debug, Clone, Copy, PartialEq)]
+#[bitpack(order = Lsb0, byte_order = Little)]
+struct Tuple(#[bits(3)] u8, Inner, u8);
+
+#[derive(BitPack, Debug, PartialEq)]
+struct Empty;
+
+// Раскладка Msb0: поля идут от старших бит хранилища, значение выровнено по старшим битам.
+#[test]
+fn test1() {
+    assert_eq!(<Inner as BitPack>::BITS, 9);
+    assert_eq!(<Outer as BitPack>::BITS, 24);
+    let v = Outer { flag: true, mode: Mode::Stop, inner: Inner { a: 0x13, b: -3 }, c: 0x5A5 };
+    // 1 110 10011 1101 10110100101 и 8 нулевых бит
+    let bytes = v.pack();
+    assert_eq!(bytes, [0xE9, 0xED, 0xA5, 0x00]);
+    assert_eq!(Outer::unpack(&bytes), Ok(v));
+    assert_eq!(Inner { a: 0x13, b: -3 }.pack(), [0x9E, 0x80]);
+    assert_eq!(Empty.pack(), []);
+    assert_eq!(Empty::unpack(&[]), Ok(Empty));
+}
+
+// Раскладка Lsb0 с порядком байтов Little и вложенная структура со своей раскладкой.
+#[test]
+fn test2() {
+    let v = Tuple(0b101, Inner { a: 0x13, b: -3 }, 0xC3);
+    // S = C3 << 12 | (10011 1101) << 3 | 101
+    let s: u32 = (0xC3 << 12) | (0b1_0011_1101 << 3) | 0b101;
+    assert_eq!(v.pack(), s.to_le_bytes()[..3]);
+    assert_eq!(Tuple::unpack(&v.pack()), Ok(v));
+
+    let mut bytes = [0xFFu8; 3];
+    v.write_bits(&mut bytes, ByteOrder::Big, 1);
+    assert_eq!(Tuple::read_bits(&bytes, ByteOrder::Big, 1), Ok(v));
+    assert_eq!(bytes[2] & 1, 1);
+}
+
+// Ошибки распаковки и усечение значений, не помещающихся в поле.
+#[test]
+fn test3() {
+    assert_eq!(Outer::unpack(&[0; 3]), Err(UnpackError::SrcTooShort { len: 3, required: 4 }));
+    assert_eq!(Outer::unpack(&[0; 4]), Err(UnpackError::InvalidDiscriminant { value: 0 }));
+    assert_eq!(Mode::unpack(&[0x40]), Ok(Mode::Run));
+    assert_eq!(Mode::unpack(&[0xE0]), Err(UnpackError::InvalidDiscriminant { value: 7 }));
+
+    let v = Inner { a: 0xFF, b: 9 };
+    assert_eq!(Inner::unpack(&v.pack()), Ok(Inner { a: 0x1F, b: -7 }));
+}
+
+// try_pack отклоняет значения, которые pack усекает, в том числе во вложенных структурах.
+#[test]
+fn test4() {
+    assert_eq!(Inner { a: 0xFF, b: 0 }.try_pack(), Err(PackError::ValueOutOfRange { field: "a" }));
+    assert_eq!(Inner { a: 0, b: 9 }.try_pack(), Err(PackError::ValueOutOfRange { field: "b" }));
+    assert_eq!(Inner { a: 0, b: -9 }.check_fits(), Err(PackError::ValueOutOfRange { field: "b" }));
+    let v = Inner { a: 0x1F, b: -8 };
+    assert_eq!(v.try_pack(), Ok(v.pack()));
+
+    let v = Outer { flag: true, mode: Mode::Run, inner: Inner { a: 0, b: 8 }, c: 0x7FF };
+    assert_eq!(v.try_pack(), Err(PackError::ValueOutOfRange { field: "b" }));
+    let v = Outer { inner: Inner { a: 0, b: 7 }, c: 0x800, ..v };
+    assert_eq!(v.try_pack(), Err(PackError::ValueOutOfRange { field: "c" }));
+    assert_eq!(Tuple(8, v.inner, 0xFF).try_pack(), Err(PackError::ValueOutOfRange { field: "0" }));
+    assert_eq!(Mode::Stop.try_pack(), Ok(Mode::Stop.pack()));
+    assert_eq!(Empty.try_pack(), Ok([]));
+}
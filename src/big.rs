@@ -0,0 +1,91 @@
+//! Большие целые числа как битовые последовательности.
+
+use alloc::{vec, vec::Vec};
+
+use num::BigUint;
+
+use crate::{repack_into_with, repack_with, validate_widths, RepackError, RepackOptions, Word};
+
+/// Разбивает число n, записанное в bits бит (начиная со старшего, с ведущими
+/// нулями), на эл-ты по bits_out значащих бит, как [`repack`](crate::repack).
+///
+/// # Errors
+/// * [`RepackError::ZeroWidth`] - bits или bits_out равен нулю.
+/// * [`RepackError::BitsOutTooLarge`] - bits_out больше размера T.
+/// * [`RepackError::UnalignedBitsLimit`] - bits не делится на bits_out.
+/// * [`RepackError::IntegerTooWide`] - n не помещается в bits бит.
+///
+/// # Examples
+///
+/// ```
+///     use num::BigUint;
+///     let n = BigUint::from(0x1_2345u32);
+///     let r: Vec<u8> = bits_rs::biguint_to_words(&n, 24, 4).unwrap();
+///     assert_eq!(r, [0, 1, 2, 3, 4, 5]);
+/// ```
+pub fn biguint_to_words<T: Word>(n: &BigUint, bits: usize, bits_out: usize) -> Result<Vec<T>, RepackError> {
+    validate_widths::<u8, T>(8, bits_out, bits)?;
+    let required = n.bits() as usize;
+    if required > bits {
+        return Err(RepackError::IntegerTooWide { bits, required });
+    }
+
+    // Байты числа, дополненные ведущими нулями до целого кол-ва байтов.
+    let len = bits.div_ceil(8);
+    let mut bytes = vec![0u8; len];
+    if required > 0 {
+        let be = n.to_bytes_be();
+        bytes[len - be.len()..].copy_from_slice(&be);
+    }
+    repack_with(&bytes, 8, bits_out, bits, RepackOptions::new().src_offset(len * 8 - bits))
+}
+
+/// Число, записанное первыми bits_limit битами последовательности
+/// (по bits_in значащих бит из каждого эл-та src), начиная со старшего.
+///
+/// # Errors
+/// * [`RepackError::ZeroWidth`] - bits_in или bits_limit равен нулю.
+/// * [`RepackError::BitsInTooLarge`] - bits_in больше размера T.
+///
+/// # Examples
+///
+/// ```
+///     use num::BigUint;
+///     let n = bits_rs::words_to_biguint(&[0u8, 1, 2, 3, 4, 5], 4, 24).unwrap();
+///     assert_eq!(n, BigUint::from(0x1_2345u32));
+/// ```
+pub fn words_to_biguint<T: Word>(src: &[T], bits_in: usize, bits_limit: usize) -> Result<BigUint, RepackError> {
+    // Последовательность выравнивается по концу последнего байта.
+    let len = bits_limit.div_ceil(8);
+    let mut bytes = vec![0u8; len];
+    let options = RepackOptions::new().dst_offset(len * 8 - bits_limit);
+    repack_into_with(src, bits_in, &mut bytes, 8, bits_limit, options)?;
+    Ok(BigUint::from_bytes_be(&bytes))
+}
+
+// Преобразование туда и обратно для разных длин и ширин.
+#[test]
+fn test1() {
+    let n = (0..40u32).fold(BigUint::from(1u8), |acc, i| (acc << 7u32) + BigUint::from(i * 3 % 128));
+    assert_eq!(n.bits(), 281);
+    for (bits, bits_out) in [(288, 8), (287, 7), (300, 5), (512, 128), (281, 1)] {
+        let words: Vec<u128> = biguint_to_words(&n, bits, bits_out).unwrap();
+        assert_eq!(words.len(), bits / bits_out);
+        assert_eq!(words_to_biguint(&words, bits_out, bits), Ok(n.clone()), "{} {}", bits, bits_out);
+    }
+    let zero = BigUint::from(0u8);
+    assert_eq!(biguint_to_words::<u8>(&zero, 12, 4), Ok(vec![0, 0, 0]));
+    assert_eq!(words_to_biguint(&[0xABCDu16], 16, 12), Ok(BigUint::from(0xABCu16)));
+}
+
+// Число шире заданной длины и ошибки параметров.
+#[test]
+fn test2() {
+    let n = BigUint::from(0x1FFu16);
+    let r = biguint_to_words::<u8>(&n, 8, 8);
+    assert_eq!(r, Err(RepackError::IntegerTooWide { bits: 8, required: 9 }));
+    let r = biguint_to_words::<u8>(&n, 10, 4);
+    assert_eq!(r, Err(RepackError::UnalignedBitsLimit { bits_limit: 10, bits_out: 4 }));
+    let r = words_to_biguint(&[1u8], 9, 8);
+    assert_eq!(r, Err(RepackError::BitsInTooLarge { bits_in: 9, size: 8 }));
+}
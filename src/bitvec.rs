@@ -0,0 +1,313 @@
+//! Битовая последовательность произвольной длины.
+
+#[cfg(test)]
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Bound, RangeBounds};
+
+use crate::{
+    repack, repack_into_with, repacked_len_with, validate_widths, BitSlice, BitSliceMut, Padding, RepackError,
+    RepackOptions, Word,
+};
+
+/// Битовая последовательность произвольной длины.
+///
+/// Биты хранятся по 8 в байте, начиная со старшего (как в
+/// [`repack`](crate::repack) при bits_out = 8), неиспользуемые биты последнего
+/// байта всегда нулевые. Сравнение, хеширование и упорядочивание учитывают
+/// точную длину: `0b1` и `0b10` - разные последовательности, и первая меньше
+/// (как префикс).
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::BitVec;
+///     let mut bits = BitVec::from_words(&[5u16, 5], 3).unwrap(); // 101101
+///     bits.push(true);
+///     assert_eq!(bits.len(), 7);
+///     assert_eq!(format!("{:?}", bits), "0b1011011");
+///     assert_eq!(bits.to_words::<u8>(7).unwrap(), [0b_101_1011]);
+/// ```
+// Порядок полей важен: производный Ord сравнивает сначала байты, затем длину,
+// что совпадает с лексикографическим порядком битов, т.к. хвост заполнен нулями.
+#[derive(Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BitVec {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitVec {
+    /// Пустая последовательность.
+    pub const fn new() -> Self {
+        BitVec {
+            bytes: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Пустая последовательность с местом под bits бит.
+    pub fn with_capacity(bits: usize) -> Self {
+        BitVec {
+            bytes: Vec::with_capacity(bits.div_ceil(8)),
+            len: 0,
+        }
+    }
+
+    /// Последовательность из первых len бит bytes.
+    ///
+    /// # Panics
+    /// Если в bytes меньше len бит.
+    pub fn from_bytes(mut bytes: Vec<u8>, len: usize) -> Self {
+        assert!(len <= bytes.len() * 8, "len {} > {} bits", len, bytes.len() * 8);
+        bytes.truncate(len.div_ceil(8));
+        if !len.is_multiple_of(8) {
+            let last = bytes.len() - 1;
+            bytes[last] &= 0xFF << (8 - len % 8);
+        }
+        BitVec { bytes, len }
+    }
+
+    /// Последовательность из src, по bits_in значащих бит из каждого эл-та
+    /// (как входной срез [`repack`](crate::repack)).
+    ///
+    /// # Errors
+    /// * [`RepackError::ZeroWidth`] - bits_in равен нулю.
+    /// * [`RepackError::BitsInTooLarge`] - bits_in больше размера T.
+    pub fn from_words<T: Word>(src: &[T], bits_in: usize) -> Result<Self, RepackError> {
+        let mut bits = BitVec::with_capacity(src.len() * bits_in);
+        bits.extend_from_bits(src, bits_in, src.len() * bits_in)?;
+        Ok(bits)
+    }
+
+    /// Эл-ты по bits_out значащих бит (как результат [`repack`](crate::repack)
+    /// с bits_limit = self.len()).
+    ///
+    /// # Errors
+    /// * [`RepackError::ZeroWidth`] - bits_out равен нулю.
+    /// * [`RepackError::BitsOutTooLarge`] - bits_out больше размера T.
+    /// * [`RepackError::UnalignedBitsLimit`] - длина не делится на bits_out.
+    pub fn to_words<T: Word>(&self, bits_out: usize) -> Result<Vec<T>, RepackError> {
+        if self.len == 0 {
+            validate_widths::<u8, T>(8, bits_out, 0)?;
+            return Ok(Vec::new());
+        }
+        repack(&self.bytes, 8, bits_out, self.len)
+    }
+
+    /// Дописывает в конец bits_limit бит из src, по bits_in значащих бит
+    /// из каждого эл-та. Если в src меньше bits_limit бит, недостающие биты
+    /// заполняются нулями, как в [`repack`](crate::repack).
+    ///
+    /// # Errors
+    /// * [`RepackError::ZeroWidth`] - bits_in равен нулю.
+    /// * [`RepackError::BitsInTooLarge`] - bits_in больше размера T.
+    pub fn extend_from_bits<T: Word>(&mut self, src: &[T], bits_in: usize, bits_limit: usize) -> Result<(), RepackError> {
+        validate_widths::<T, u8>(bits_in, 8, bits_limit)?;
+        if bits_limit == 0 {
+            return Ok(());
+        }
+
+        let options = RepackOptions::new().dst_offset(self.len).padding(Padding::PadZeros);
+        self.bytes.resize(repacked_len_with(8, bits_limit, options), 0);
+        repack_into_with(src, bits_in, &mut self.bytes, 8, bits_limit, options)?;
+        self.len += bits_limit;
+        Ok(())
+    }
+
+    /// Кол-во бит.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Пуста ли последовательность.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Байты последовательности. Неиспользуемые биты последнего байта нулевые.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Байты последовательности. Неиспользуемые биты последнего байта нулевые.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Бит с номером i или None, если i за пределами последовательности.
+    pub fn get(&self, i: usize) -> Option<bool> {
+        if i < self.len {
+            Some(self.bytes[i / 8] & (0x80 >> (i % 8)) != 0)
+        } else {
+            None
+        }
+    }
+
+    /// Устанавливает бит с номером i.
+    ///
+    /// # Panics
+    /// Если i за пределами последовательности.
+    pub fn set(&mut self, i: usize, bit: bool) {
+        assert!(i < self.len, "index {} out of range for BitVec of length {}", i, self.len);
+        let mask = 0x80 >> (i % 8);
+        if bit {
+            self.bytes[i / 8] |= mask;
+        } else {
+            self.bytes[i / 8] &= !mask;
+        }
+    }
+
+    /// Дописывает бит в конец.
+    pub fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        self.len += 1;
+        self.set(self.len - 1, bit);
+    }
+
+    /// Удаляет и возвращает последний бит.
+    pub fn pop(&mut self) -> Option<bool> {
+        let bit = self.get(self.len.checked_sub(1)?)?;
+        self.set(self.len - 1, false);
+        self.len -= 1;
+        self.bytes.truncate(self.len.div_ceil(8));
+        Some(bit)
+    }
+
+    /// Копия части последовательности.
+    ///
+    /// # Panics
+    /// Если диапазон выходит за пределы последовательности.
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> BitVec {
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i + 1,
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => self.len,
+        };
+        assert!(start <= end && end <= self.len, "range {}..{} out of range for BitVec of length {}", start, end, self.len);
+
+        let mut bits = BitVec::with_capacity(end - start);
+        if end > start {
+            let options = RepackOptions::new().src_offset(start).padding(Padding::PadZeros);
+            bits.bytes.resize((end - start).div_ceil(8), 0);
+            bits.len = end - start;
+            repack_into_with(&self.bytes, 8, &mut bits.bytes, 8, end - start, options)
+                .expect("bytes are always valid repack input");
+        }
+        bits
+    }
+
+    /// Представление битов без копирования.
+    pub fn as_bit_slice(&self) -> BitSlice<'_, u8> {
+        BitSlice::new(&self.bytes, 8)
+            .expect("u8 holds 8 bits")
+            .slice(..self.len)
+    }
+
+    /// Изменяемое представление битов без копирования.
+    pub fn as_mut_bit_slice(&mut self) -> BitSliceMut<'_, u8> {
+        // Хвост последнего байта в представление не входит и остается нулевым.
+        let len = self.len;
+        BitSliceMut::new(&mut self.bytes, 8).expect("u8 holds 8 bits").prefix(len)
+    }
+
+    /// Итератор по битам.
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(move |i| self.bytes[i / 8] & (0x80 >> (i % 8)) != 0)
+    }
+}
+
+impl fmt::Debug for BitVec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("0b")?;
+        for bit in self.iter() {
+            f.write_str(if bit { "1" } else { "0" })?;
+        }
+        Ok(())
+    }
+}
+
+impl Extend<bool> for BitVec {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, iter: I) {
+        for bit in iter {
+            self.push(bit);
+        }
+    }
+}
+
+impl FromIterator<bool> for BitVec {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bits = BitVec::new();
+        bits.extend(iter);
+        bits
+    }
+}
+
+// push/pop/get/set.
+#[test]
+fn test1() {
+    let mut bits = BitVec::new();
+    for i in 0..20 {
+        bits.push(i % 3 == 0);
+    }
+    assert_eq!(bits.len(), 20);
+    assert_eq!(bits.get(3), Some(true));
+    assert_eq!(bits.get(4), Some(false));
+    assert_eq!(bits.get(20), None);
+    bits.set(4, true);
+    assert_eq!(bits.get(4), Some(true));
+    assert_eq!(bits.pop(), Some(false));
+    assert_eq!(bits.pop(), Some(true));
+    assert_eq!(bits.len(), 18);
+    assert_eq!(bits.as_bytes(), [0b_1001_1010, 0b_0100_1001, 0]);
+    assert_eq!(BitVec::new().pop(), None);
+}
+
+// Преобразования в эл-ты и обратно, срезы.
+#[test]
+fn test2() {
+    let bits = BitVec::from_words(&[0x123u16, 0x456, 0x789], 12).unwrap();
+    assert_eq!(bits.len(), 36);
+    assert_eq!(bits.to_words::<u8>(4).unwrap(), [1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    assert_eq!(
+        bits.to_words::<u8>(8),
+        Err(RepackError::UnalignedBitsLimit { bits_limit: 36, bits_out: 8 })
+    );
+    assert_eq!(bits.slice(4..16).to_words::<u16>(12).unwrap(), [0x234]);
+    assert_eq!(bits.slice(33..), BitVec::from_words(&[1u8], 3).unwrap());
+    assert!(bits.slice(5..5).is_empty());
+
+    let mut tail = bits.slice(..3);
+    tail.extend_from_bits(&[0xFFu8], 8, 10).unwrap();
+    assert_eq!(format!("{:?}", tail), "0b0001111111100");
+}
+
+// Сравнение учитывает точную длину.
+#[test]
+fn test3() {
+    let a: BitVec = [true].into_iter().collect();
+    let b: BitVec = [true, false].into_iter().collect();
+    let c: BitVec = [false, true, true].into_iter().collect();
+    assert_ne!(a, b);
+    assert!(a < b);
+    assert!(c < a);
+    assert_eq!(BitVec::from_bytes(alloc::vec![0xFF], 1), a);
+    assert_eq!(format!("{:?}", BitVec::new()), "0b");
+}
+
+// Представления BitVec не выходят за его длину.
+#[test]
+fn test4() {
+    let mut bits = BitVec::from_words(&[0b_101u8], 3).unwrap();
+    assert_eq!(bits.as_bit_slice().len(), 3);
+    bits.as_mut_bit_slice().set(1, true);
+    assert_eq!(bits.as_bytes(), [0b_1110_0000]);
+    assert_eq!(bits.as_bit_slice().to_bitvec(), bits);
+}
@@ -0,0 +1,145 @@
+//! Срезы `bool` как битовые последовательности.
+
+use alloc::vec::Vec;
+
+use crate::{repack_with, validate_widths, window_end, BitOrder, RepackError, RepackOptions, Word};
+
+/// Упаковывает биты src в эл-ты по bits_out значащих бит, как
+/// [`repack`](crate::repack) с bits_in = 1 и bits_limit = src.len().
+///
+/// Пустой src дает пустой результат.
+///
+/// # Errors
+/// * [`RepackError::ZeroWidth`] - bits_out равен нулю.
+/// * [`RepackError::BitsOutTooLarge`] - bits_out больше размера T.
+/// * [`RepackError::UnalignedBitsLimit`] - src.len() не делится на bits_out.
+///
+/// # Examples
+///
+/// ```
+///     let bits = [true, false, true, true, false, true];
+///     let r: Vec<u8> = bits_rs::bits_to_words(&bits, 2).unwrap();
+///     assert_eq!(r, [0b_10, 0b_11, 0b_01]);
+/// ```
+pub fn bits_to_words<T: Word>(src: &[bool], bits_out: usize) -> Result<Vec<T>, RepackError> {
+    if src.is_empty() {
+        validate_widths::<u8, T>(1, bits_out, 0)?;
+        return Ok(Vec::new());
+    }
+    bits_to_words_with(src, bits_out, src.len(), RepackOptions::new())
+}
+
+/// То же, что и [`bits_to_words`], но с ограничением кол-ва бит и
+/// дополнительными параметрами упаковки (см. [`repack_with`]).
+///
+/// Порядок битов входного среза не учитывается: каждый эл-т src - один бит.
+/// Строгая проверка длины сравнивает src_offset + bits_limit с src.len().
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{Padding, RepackOptions};
+///     let bits = [false, true, true, false, true];
+///     let options = RepackOptions::new().src_offset(1).padding(Padding::PadZeros);
+///     let r: Vec<u8> = bits_rs::bits_to_words_with(&bits, 3, 4, options).unwrap();
+///     assert_eq!(r, [0b_110, 0b_100]);
+/// ```
+pub fn bits_to_words_with<T: Word>(
+    src: &[bool],
+    bits_out: usize,
+    bits_limit: usize,
+    options: RepackOptions,
+) -> Result<Vec<T>, RepackError> {
+    let end = window_end(options.get_src_offset(), bits_limit)?;
+    if options.get_strict_length() && src.len() < end {
+        return Err(RepackError::SrcTooShort { available: src.len(), required: end });
+    }
+
+    // Биты собираются в байты, чтобы упаковать их repack_with.
+    let bytes: Vec<u8> = src
+        .chunks(8)
+        .map(|chunk| chunk.iter().enumerate().fold(0, |b, (i, &bit)| b | ((bit as u8) << (7 - i))))
+        .collect();
+    repack_with(&bytes, 8, bits_out, bits_limit, options.src_order(BitOrder::Msb0).strict_length(false))
+}
+
+/// Биты эл-тов src, по bits_in значащих бит из каждого, как
+/// [`repack`](crate::repack) с bits_out = 1 и bits_limit = src.len() * bits_in.
+///
+/// Пустой src дает пустой результат.
+///
+/// # Errors
+/// * [`RepackError::ZeroWidth`] - bits_in равен нулю.
+/// * [`RepackError::BitsInTooLarge`] - bits_in больше размера T.
+///
+/// # Examples
+///
+/// ```
+///     let r = bits_rs::words_to_bits(&[5u16, 1], 3).unwrap();
+///     assert_eq!(r, [true, false, true, false, false, true]);
+/// ```
+pub fn words_to_bits<T: Word>(src: &[T], bits_in: usize) -> Result<Vec<bool>, RepackError> {
+    if src.is_empty() {
+        validate_widths::<T, u8>(bits_in, 1, 0)?;
+        return Ok(Vec::new());
+    }
+    words_to_bits_with(src, bits_in, src.len() * bits_in, RepackOptions::new())
+}
+
+/// То же, что и [`words_to_bits`], но с ограничением кол-ва бит и
+/// дополнительными параметрами упаковки (см. [`repack_with`]).
+///
+/// Если бит в src меньше bits_limit, недостающие биты равны `false`.
+/// При dst_offset результат начинается с dst_offset битов `false`.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{BitOrder, RepackOptions};
+///     let options = RepackOptions::new().src_order(BitOrder::Lsb0).src_offset(2);
+///     let r = bits_rs::words_to_bits_with(&[0b_0110u8], 4, 3, options).unwrap();
+///     assert_eq!(r, [true, false, false]);
+/// ```
+pub fn words_to_bits_with<T: Word>(
+    src: &[T],
+    bits_in: usize,
+    bits_limit: usize,
+    options: RepackOptions,
+) -> Result<Vec<bool>, RepackError> {
+    let bits: Vec<u8> = repack_with(src, bits_in, 1, bits_limit, options)?;
+    Ok(bits.into_iter().map(|b| b != 0).collect())
+}
+
+// Результат совпадает с repack битов, представленных эл-тами u8.
+#[test]
+fn test1() {
+    let bits: Vec<bool> = (0..50u32).map(|i| i.wrapping_mul(2654435761) >> 31 != 0).collect();
+    let ones: Vec<u8> = bits.iter().map(|&b| b as u8).collect();
+    for bits_out in [1, 2, 5, 10, 25, 50] {
+        let r: Vec<u64> = bits_to_words(&bits, bits_out).unwrap();
+        assert_eq!(r, crate::repack::<u8, u64>(&ones, 1, bits_out, 50).unwrap());
+        assert_eq!(words_to_bits(&r, bits_out), Ok(bits.clone()));
+    }
+    let options = RepackOptions::new().src_offset(7).dst_offset(3).padding(crate::Padding::PadOnes);
+    let r: Vec<u16> = bits_to_words_with(&bits, 12, 40, options).unwrap();
+    assert_eq!(r, crate::repack_with::<u8, u16>(&ones, 1, 12, 40, options).unwrap());
+}
+
+// Пустые срезы, нехватка бит и ошибки параметров.
+#[test]
+fn test2() {
+    assert_eq!(bits_to_words::<u8>(&[], 3), Ok(Vec::new()));
+    assert_eq!(words_to_bits::<u8>(&[], 3), Ok(Vec::new()));
+    let r = bits_to_words::<u8>(&[], 9);
+    assert_eq!(r, Err(RepackError::BitsOutTooLarge { bits_out: 9, size: 8 }));
+    let r = bits_to_words::<u8>(&[true; 5], 2);
+    assert_eq!(r, Err(RepackError::UnalignedBitsLimit { bits_limit: 5, bits_out: 2 }));
+
+    let options = RepackOptions::new().strict_length(true);
+    let r = bits_to_words_with::<u8>(&[true; 5], 3, 6, options);
+    assert_eq!(r, Err(RepackError::SrcTooShort { available: 5, required: 6 }));
+    let r = bits_to_words_with::<u8>(&[true; 5], 3, 6, RepackOptions::new());
+    assert_eq!(r, Ok(alloc::vec![0b_111, 0b_110]));
+    let r = words_to_bits_with(&[1u8], 1, 3, RepackOptions::new().dst_offset(1));
+    assert_eq!(r, Ok(alloc::vec![false, true, false, false]));
+}
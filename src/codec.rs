@@ -0,0 +1,1095 @@
+//! Компактный битовый формат данных для serde.
+//!
+//! Номер варианта перечисления из n вариантов занимает ceil(log2(n)) бит,
+//! только если перечисление указано в таблице [`SerdeOptions::variant_counts`]:
+//! serde не сообщает сериализатору кол-во вариантов. По умолчанию таблица пуста,
+//! и номера вариантов записываются varint.
+//!
+//! Значения записываются подряд, без выравнивания и без описания типов:
+//! * `bool` и признак `Some` у `Option` - 1 бит;
+//! * целые числа - все биты типа или varint (см. [`IntEncoding`]);
+//! * `f32`, `f64` - 32 и 64 бита IEEE 754, `char` - 21 бит;
+//! * строки, байты, последовательности и словари - длина (varint с группами
+//!   по [`SerdeOptions::len_group`] бит), затем эл-ты;
+//! * кортежи и структуры - поля по порядку, `()` и пустые структуры - 0 бит;
+//! * номер варианта перечисления из n вариантов - ceil(log2(n)) бит, если
+//!   перечисление есть в таблице [`SerdeOptions::variant_counts`], иначе
+//!   varint, как длина; затем содержимое варианта.
+//!
+//! Таблица ищет перечисление по имени, которое serde передает без пути
+//! модуля (`#[serde(rename = "...")]` заменяет его), и имена в ней не должны
+//! повторяться. Сериализатор не может отличить одноименные перечисления из
+//! разных модулей, поэтому каждому перечислению, которое сериализуется с
+//! таблицей, нужно уникальное имя (при необходимости заданное через `rename`).
+//!
+//! Формат не самоописывающий, поэтому `deserialize_any` (например,
+//! `#[serde(untagged)]` и `#[serde(flatten)]`) не поддерживается.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+use crate::raw::{mask, shl, shr};
+use crate::{BitReader, BitWriter, ByteSink, ByteSource, Padding, SerdeError, Word};
+
+/// Кодирование целых чисел.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum IntEncoding {
+    /// Все биты типа: 8 для `u8` и `i8`, 32 для `u32` и `i32` и т. д.
+    #[default]
+    Fixed,
+    /// Группы по n бит, начиная с младшей; после каждой группы идет бит
+    /// продолжения (1 - за ней есть еще группа). Знаковые числа
+    /// предварительно отображаются в беззнаковые (zigzag): 0, -1, 1, -2, ...
+    /// в 0, 1, 2, 3, ...
+    Varint(usize),
+}
+
+/// Параметры битового формата serde.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{IntEncoding, SerdeOptions};
+///     let options = SerdeOptions::new().ints(IntEncoding::Varint(3)).len_group(2);
+///     assert_eq!(options.get_ints(), IntEncoding::Varint(3));
+///     assert_eq!(options.get_variant_counts(), []);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SerdeOptions {
+    ints: IntEncoding,
+    len_group: usize,
+    variant_counts: &'static [(&'static str, usize)],
+}
+
+impl Default for SerdeOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SerdeOptions {
+    /// Параметры по умолчанию: целые числа фиксированной ширины, длины
+    /// группами по 4 бита, таблица вариантов пуста.
+    pub const fn new() -> Self {
+        SerdeOptions {
+            ints: IntEncoding::Fixed,
+            len_group: 4,
+            variant_counts: &[],
+        }
+    }
+
+    /// Кодирование целых чисел.
+    pub const fn ints(mut self, ints: IntEncoding) -> Self {
+        self.ints = ints;
+        self
+    }
+
+    /// Ширина группы varint, которым записываются длины и номера вариантов
+    /// перечислений, отсутствующих в таблице.
+    pub const fn len_group(mut self, bits: usize) -> Self {
+        self.len_group = bits;
+        self
+    }
+
+    /// Таблица (имя перечисления, кол-во вариантов). Номер варианта
+    /// перечисления из таблицы записывается ceil(log2(n)) битами.
+    ///
+    /// Сериализатор не знает кол-ва вариантов перечисления, поэтому оно
+    /// задается здесь; при десериализации оно сверяется с типом.
+    ///
+    /// Имя - то, что serde передает сериализатору: имя типа без пути модуля
+    /// или значение `#[serde(rename = "...")]`. Строка таблицы относится ко
+    /// всем перечислениям с этим именем, поэтому у сериализуемых перечислений
+    /// имена должны быть уникальны (одноименным нужны разные имена `rename`).
+    /// Сериализатор не может обнаружить нарушение: он запишет номер варианта
+    /// шириной из чужой строки таблицы, и ошибку
+    /// [`SerdeError::VariantCount`] вернет только десериализация.
+    ///
+    /// # Panics
+    /// Если имя в таблице повторяется (при вызове в константном выражении -
+    /// ошибка компиляции).
+    pub const fn variant_counts(mut self, counts: &'static [(&'static str, usize)]) -> Self {
+        let mut i = 0;
+        while i < counts.len() {
+            let mut j = 0;
+            while j < i {
+                assert!(!str_eq(counts[i].0, counts[j].0), "duplicate enum name in variant_counts");
+                j += 1;
+            }
+            i += 1;
+        }
+        self.variant_counts = counts;
+        self
+    }
+
+    /// Кодирование целых чисел.
+    pub const fn get_ints(&self) -> IntEncoding {
+        self.ints
+    }
+
+    /// Ширина группы varint для длин.
+    pub const fn get_len_group(&self) -> usize {
+        self.len_group
+    }
+
+    /// Таблица кол-ва вариантов перечислений.
+    pub const fn get_variant_counts(&self) -> &'static [(&'static str, usize)] {
+        self.variant_counts
+    }
+
+    // Кол-во вариантов перечисления name из таблицы.
+    fn variant_count(&self, name: &str) -> Option<usize> {
+        self.variant_counts.iter().find(|&&(n, _)| n == name).map(|&(_, count)| count)
+    }
+}
+
+// Сравнение строк, доступное в константных функциях.
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+// Кол-во бит номера варианта: ceil(log2(count)).
+fn variant_bits(count: usize) -> usize {
+    (usize::BITS - count.saturating_sub(1).leading_zeros()) as usize
+}
+
+fn check_group(group: usize) -> Result<(), SerdeError> {
+    if group == 0 || group > 128 {
+        return Err(SerdeError::InvalidGroup { bits: group });
+    }
+    Ok(())
+}
+
+/// Сериализует значение в байты в порядке [`BitOrder::Msb0`](crate::BitOrder::Msb0)
+/// с параметрами по умолчанию. Неполный последний байт дополняется нулями.
+///
+/// Таблица [`SerdeOptions::variant_counts`] по умолчанию пуста, и номера
+/// вариантов всех перечислений записываются varint, так что одноименные
+/// перечисления из разных модулей не мешают друг другу (см. [`to_bytes_with`]).
+///
+/// # Errors
+/// * [`SerdeError::LengthRequired`] - длина последовательности неизвестна заранее.
+/// * [`SerdeError::Message`] - ошибка реализации `Serialize`.
+///
+/// # Examples
+///
+/// ```
+///     let r = bits_rs::to_bytes(&(true, Some(5u8), [false, true])).unwrap();
+///     // 1 1 00000101 0 1 и 4 нулевых бита
+///     assert_eq!(r, [0b_1100_0001, 0b_0101_0000]);
+/// ```
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SerdeError> {
+    to_bytes_with(value, SerdeOptions::new())
+}
+
+/// То же, что и [`to_bytes`], но с заданными параметрами формата.
+///
+/// Номера вариантов занимают ceil(log2(n)) бит только у перечислений из
+/// таблицы [`SerdeOptions::variant_counts`]. Перечисления ищутся в ней по
+/// имени без пути модуля, поэтому имена сериализуемых перечислений должны быть
+/// уникальны (см. [`SerdeOptions::variant_counts`]).
+///
+/// # Errors
+/// Те же, что и у [`to_bytes`], а также [`SerdeError::InvalidGroup`] и
+/// [`SerdeError::InvalidVariant`] (номер варианта не меньше кол-ва
+/// вариантов из таблицы).
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{IntEncoding, SerdeOptions};
+///     let options = SerdeOptions::new().ints(IntEncoding::Varint(3));
+///     // 3 = 011 0; -3 (zigzag 5) = 101 0
+///     let r = bits_rs::to_bytes_with(&(3u64, -3i32), options).unwrap();
+///     assert_eq!(r, [0b_0110_1010]);
+/// ```
+pub fn to_bytes_with<T: Serialize + ?Sized>(value: &T, options: SerdeOptions) -> Result<Vec<u8>, SerdeError> {
+    let mut serializer = BitSerializer::new(BitWriter::new(Vec::new()), options);
+    value.serialize(&mut serializer)?;
+    let mut writer = serializer.into_inner();
+    writer.finish(Padding::PadZeros)?;
+    Ok(writer.into_inner())
+}
+
+/// Десериализует значение, записанное [`to_bytes`].
+///
+/// # Errors
+/// * [`SerdeError::Read`] - данные закончились раньше значения.
+/// * [`SerdeError::TrailingBytes`] - после значения остались целые байты.
+/// * [`SerdeError::InvalidChar`], [`SerdeError::InvalidUtf8`],
+///   [`SerdeError::InvalidVariant`] - некорректные данные.
+/// * [`SerdeError::AnyNotSupported`] - тип требует самоописывающего формата.
+///
+/// # Examples
+///
+/// ```
+///     let r: (bool, Option<u8>, [bool; 2]) = bits_rs::from_bytes(&[0b_1100_0001, 0b_0101_0000]).unwrap();
+///     assert_eq!(r, (true, Some(5), [false, true]));
+/// ```
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, SerdeError> {
+    from_bytes_with(bytes, SerdeOptions::new())
+}
+
+/// То же, что и [`from_bytes`], но с заданными параметрами формата.
+///
+/// # Errors
+/// Те же, что и у [`from_bytes`], а также [`SerdeError::InvalidGroup`],
+/// [`SerdeError::IntegerOverflow`] и [`SerdeError::VariantCount`].
+pub fn from_bytes_with<T: DeserializeOwned>(bytes: &[u8], options: SerdeOptions) -> Result<T, SerdeError> {
+    let mut deserializer = BitDeserializer::new(BitReader::new(bytes), options);
+    let value = T::deserialize(&mut deserializer)?;
+    let len = deserializer.into_inner().remaining().unwrap_or(0) / 8;
+    if len > 0 {
+        return Err(SerdeError::TrailingBytes { len });
+    }
+    Ok(value)
+}
+
+/// Сериализатор serde в битовый поток [`BitWriter`].
+///
+/// Значения записываются в поток одно за другим, порядок битов задается
+/// писателем. После записи последнего значения неполный байт выдается
+/// [`BitWriter::finish`].
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{BitSerializer, BitWriter, Padding, SerdeOptions};
+///     use serde::Serialize;
+///
+///     #[derive(Serialize)]
+///     enum Kind {
+///         Ping,
+///         Data(u8),
+///         Ack { id: bool },
+///     }
+///
+///     let options = SerdeOptions::new().variant_counts(&[("Kind", 3)]);
+///     let mut serializer = BitSerializer::new(BitWriter::new(Vec::new()), options);
+///     Kind::Data(0x5A).serialize(&mut serializer).unwrap();
+///     Kind::Ack { id: true }.serialize(&mut serializer).unwrap();
+///     Kind::Ping.serialize(&mut serializer).unwrap();
+///     let mut writer = serializer.into_inner();
+///     assert_eq!(writer.position(), 15);
+///     writer.finish(Padding::PadZeros).unwrap();
+///     // 01 01011010 10 1 00 и 1 нулевой бит
+///     assert_eq!(writer.into_inner(), [0b_0101_0110, 0b_1010_1000]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BitSerializer<S> {
+    writer: BitWriter<S>,
+    options: SerdeOptions,
+}
+
+impl<S: ByteSink> BitSerializer<S> {
+    /// Сериализатор, пишущий в writer.
+    pub fn new(writer: BitWriter<S>, options: SerdeOptions) -> Self {
+        BitSerializer { writer, options }
+    }
+
+    /// Писатель.
+    pub fn get_ref(&self) -> &BitWriter<S> {
+        &self.writer
+    }
+
+    /// Писатель. Неполный последний байт еще не записан в приемник.
+    pub fn into_inner(self) -> BitWriter<S> {
+        self.writer
+    }
+
+    fn write_varint(&mut self, v: u128, group: usize) -> Result<(), SerdeError> {
+        check_group(group)?;
+        let mut v = v;
+        loop {
+            self.writer.write_bits(v & mask(group), group)?;
+            v = shr(v, group);
+            self.writer.write_bool(v != 0)?;
+            if v == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    fn write_int<T: Word>(&mut self, v: T) -> Result<(), SerdeError> {
+        match self.options.ints {
+            IntEncoding::Fixed => Ok(self.writer.write_bits(v, T::BITS)?),
+            IntEncoding::Varint(group) => {
+                let raw = v.to_raw();
+                let raw = if T::SIGNED { (raw << 1) ^ ((raw as i128 >> 127) as u128) } else { raw };
+                self.write_varint(raw, group)
+            }
+        }
+    }
+
+    fn write_len(&mut self, len: usize) -> Result<(), SerdeError> {
+        self.write_varint(len as u128, self.options.len_group)
+    }
+
+    fn write_variant(&mut self, name: &str, index: u32) -> Result<(), SerdeError> {
+        match self.options.variant_count(name) {
+            Some(count) if index as usize >= count => Err(SerdeError::InvalidVariant { index: index as u128, count }),
+            Some(count) => Ok(self.writer.write_bits(index, variant_bits(count))?),
+            None => self.write_len(index as usize),
+        }
+    }
+}
+
+impl<S: ByteSink> ser::Serializer for &mut BitSerializer<S> {
+    type Ok = ();
+    type Error = SerdeError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), SerdeError> {
+        Ok(self.writer.write_bool(v)?)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), SerdeError> {
+        self.write_int(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), SerdeError> {
+        self.write_int(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), SerdeError> {
+        self.write_int(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), SerdeError> {
+        self.write_int(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), SerdeError> {
+        self.write_int(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), SerdeError> {
+        self.write_int(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), SerdeError> {
+        self.write_int(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), SerdeError> {
+        self.write_int(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), SerdeError> {
+        self.write_int(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), SerdeError> {
+        self.write_int(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), SerdeError> {
+        Ok(self.writer.write_bits(v.to_bits(), 32)?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), SerdeError> {
+        Ok(self.writer.write_bits(v.to_bits(), 64)?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SerdeError> {
+        Ok(self.writer.write_bits(v as u32, 21)?)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), SerdeError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), SerdeError> {
+        self.write_len(v.len())?;
+        Ok(self.writer.write_bytes(v)?)
+    }
+
+    fn serialize_none(self) -> Result<(), SerdeError> {
+        Ok(self.writer.write_bool(false)?)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerdeError> {
+        self.writer.write_bool(true)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerdeError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerdeError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, index: u32, _variant: &'static str) -> Result<(), SerdeError> {
+        self.write_variant(name, index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<(), SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.write_variant(name, index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, SerdeError> {
+        self.write_len(len.ok_or(SerdeError::LengthRequired)?)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, SerdeError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, SerdeError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, SerdeError> {
+        self.write_variant(name, index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, SerdeError> {
+        self.serialize_seq(len)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, SerdeError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, SerdeError> {
+        self.write_variant(name, index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+// Составные значения записываются эл-т за эл-том.
+macro_rules! impl_serialize_compound {
+    ($($trait:ident::$method:ident),*) => {$(
+        impl<S: ByteSink> ser::$trait for &mut BitSerializer<S> {
+            type Ok = ();
+            type Error = SerdeError;
+
+            fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+                value.serialize(&mut **self)
+            }
+
+            fn end(self) -> Result<(), SerdeError> {
+                Ok(())
+            }
+        }
+    )*};
+}
+
+impl_serialize_compound!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+impl<S: ByteSink> ser::SerializeMap for &mut BitSerializer<S> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        Ok(())
+    }
+}
+
+impl<S: ByteSink> ser::SerializeStruct for &mut BitSerializer<S> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<(), SerdeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        Ok(())
+    }
+}
+
+impl<S: ByteSink> ser::SerializeStructVariant for &mut BitSerializer<S> {
+    type Ok = ();
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<(), SerdeError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), SerdeError> {
+        Ok(())
+    }
+}
+
+/// Десериализатор serde из битового потока [`BitReader`].
+///
+/// Параметры формата и порядок битов читателя должны совпадать с теми,
+/// с которыми значения были записаны [`BitSerializer`].
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{BitDeserializer, BitReader, SerdeOptions};
+///     use serde::Deserialize;
+///
+///     let mut deserializer = BitDeserializer::new(BitReader::new(&[0xA5u8, 0x80][..]), SerdeOptions::new());
+///     let (a, b) = <(u8, bool)>::deserialize(&mut deserializer).unwrap();
+///     assert_eq!((a, b), (0xA5, true));
+///     assert_eq!(deserializer.get_ref().position(), 9);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BitDeserializer<S> {
+    reader: BitReader<S>,
+    options: SerdeOptions,
+}
+
+impl<S: ByteSource> BitDeserializer<S> {
+    /// Десериализатор, читающий из reader.
+    pub fn new(reader: BitReader<S>, options: SerdeOptions) -> Self {
+        BitDeserializer { reader, options }
+    }
+
+    /// Читатель.
+    pub fn get_ref(&self) -> &BitReader<S> {
+        &self.reader
+    }
+
+    /// Читатель, позиция которого - сразу за последним прочитанным значением.
+    pub fn into_inner(self) -> BitReader<S> {
+        self.reader
+    }
+
+    fn read_varint(&mut self, group: usize) -> Result<u128, SerdeError> {
+        check_group(group)?;
+        let (mut v, mut shift) = (0u128, 0usize);
+        loop {
+            let chunk: u128 = self.reader.read_bits(group)?;
+            if shr(shl(chunk, shift), shift) != chunk {
+                return Err(SerdeError::IntegerOverflow);
+            }
+            v |= shl(chunk, shift);
+            shift = shift.saturating_add(group);
+            if !self.reader.read_bool()? {
+                return Ok(v);
+            }
+        }
+    }
+
+    fn read_int<T: Word>(&mut self) -> Result<T, SerdeError> {
+        match self.options.ints {
+            IntEncoding::Fixed => Ok(self.reader.read_bits(T::BITS)?),
+            IntEncoding::Varint(group) => {
+                let raw = self.read_varint(group)?;
+                let raw = if T::SIGNED { (raw >> 1) ^ (raw & 1).wrapping_neg() } else { raw };
+                let v = T::from_raw(raw);
+                if v.to_raw() != raw {
+                    return Err(SerdeError::IntegerOverflow);
+                }
+                Ok(v)
+            }
+        }
+    }
+
+    fn read_len(&mut self) -> Result<usize, SerdeError> {
+        let len = self.read_varint(self.options.len_group)?;
+        usize::try_from(len).map_err(|_| SerdeError::IntegerOverflow)
+    }
+
+    fn read_variant(&mut self, name: &'static str, variants: &[&str]) -> Result<u32, SerdeError> {
+        let count = variants.len();
+        let index = match self.options.variant_count(name) {
+            Some(table) if table != count => return Err(SerdeError::VariantCount { name, table, variants: count }),
+            Some(_) => self.reader.read_bits(variant_bits(count))?,
+            None => self.read_varint(self.options.len_group)?,
+        };
+        if index >= count as u128 {
+            return Err(SerdeError::InvalidVariant { index, count });
+        }
+        Ok(index as u32)
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>, SerdeError> {
+        let len = self.read_len()?;
+        // Память выделяется по мере чтения: длина может быть испорчена.
+        let mut bytes = Vec::new();
+        for _ in 0..len {
+            bytes.push(self.reader.read_bits(8)?);
+        }
+        Ok(bytes)
+    }
+}
+
+macro_rules! deserialize_int {
+    ($($method:ident => $visit:ident),*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+            visitor.$visit(self.read_int()?)
+        }
+    )*};
+}
+
+impl<'de, S: ByteSource> de::Deserializer<'de> for &mut BitDeserializer<S> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, SerdeError> {
+        Err(SerdeError::AnyNotSupported)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_bool(self.reader.read_bool()?)
+    }
+
+    deserialize_int!(
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128
+    );
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_f32(f32::from_bits(self.reader.read_bits(32)?))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_f64(f64::from_bits(self.reader.read_bits(64)?))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        let value: u32 = self.reader.read_bits(21)?;
+        visitor.visit_char(char::from_u32(value).ok_or(SerdeError::InvalidChar { value })?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        let bytes = self.read_bytes()?;
+        visitor.visit_string(String::from_utf8(bytes).map_err(|_| SerdeError::InvalidUtf8)?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_byte_buf(self.read_bytes()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        if self.reader.read_bool()? {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        let left = self.read_len()?;
+        visitor.visit_seq(Access { de: self, left })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_seq(Access { de: self, left: len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_seq(Access { de: self, left: len })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        let left = self.read_len()?;
+        visitor.visit_map(Access { de: self, left })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_seq(Access { de: self, left: fields.len() })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        let index = self.read_variant(name, variants)?;
+        visitor.visit_enum(Enum { de: self, index })
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, SerdeError> {
+        Err(SerdeError::AnyNotSupported)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, SerdeError> {
+        Err(SerdeError::AnyNotSupported)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+// Эл-ты последовательности, кортежа или структуры и пары словаря;
+// left - кол-во еще не прочитанных эл-тов (пар).
+struct Access<'a, S> {
+    de: &'a mut BitDeserializer<S>,
+    left: usize,
+}
+
+impl<'de, S: ByteSource> de::SeqAccess<'de> for Access<'_, S> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, SerdeError> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+impl<'de, S: ByteSource> de::MapAccess<'de> for Access<'_, S> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, SerdeError> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, SerdeError> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+// Вариант перечисления с уже прочитанным номером.
+struct Enum<'a, S> {
+    de: &'a mut BitDeserializer<S>,
+    index: u32,
+}
+
+impl<'de, S: ByteSource> de::EnumAccess<'de> for Enum<'_, S> {
+    type Error = SerdeError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), SerdeError> {
+        let index: de::value::U32Deserializer<SerdeError> = self.index.into_deserializer();
+        Ok((seed.deserialize(index)?, self))
+    }
+}
+
+impl<'de, S: ByteSource> de::VariantAccess<'de> for Enum<'_, S> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, SerdeError> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_seq(Access { de: self.de, left: len })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_seq(Access { de: self.de, left: fields.len() })
+    }
+}
+
+// Все типы модели данных serde в разных кодированиях целых чисел и порядках битов.
+#[test]
+fn test1() {
+    use alloc::collections::BTreeMap;
+    use alloc::{string::ToString, vec};
+    use serde::Deserialize;
+
+    #[derive(serde::Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Unit;
+
+    #[derive(serde::Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Meters(f32);
+
+    #[derive(serde::Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Pair(i16, char);
+
+    #[derive(serde::Serialize, Deserialize, Debug, Clone, PartialEq)]
+    enum Event {
+        Idle,
+        Move(Meters),
+        Turn(i8, bool),
+        Report { id: u128, text: String },
+    }
+
+    #[derive(serde::Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Frame {
+        flags: (bool, bool, ()),
+        small: (u8, u16, u32, u64, usize),
+        signed: (i8, i32, i64, i128),
+        float: f64,
+        unit: Unit,
+        pair: Pair,
+        maybe: Option<Option<u8>>,
+        events: Vec<Event>,
+        map: BTreeMap<u8, String>,
+        array: [u16; 3],
+    }
+
+    let mut map = BTreeMap::new();
+    map.insert(1, "один".to_string());
+    map.insert(200, String::new());
+    let frame = Frame {
+        flags: (true, false, ()),
+        small: (0, 65535, 7, u64::MAX, 12345),
+        signed: (-128, -1, i64::MIN, i128::MAX),
+        float: -0.15625,
+        unit: Unit,
+        pair: Pair(-300, 'я'),
+        maybe: Some(None),
+        events: vec![
+            Event::Idle,
+            Event::Move(Meters(1.5)),
+            Event::Turn(-90, true),
+            Event::Report { id: u128::MAX - 1, text: "ok".to_string() },
+        ],
+        map,
+        array: [1, 2, 3],
+    };
+
+    let tables: [&'static [(&'static str, usize)]; 2] = [&[], &[("Event", 4)]];
+    for ints in [IntEncoding::Fixed, IntEncoding::Varint(1), IntEncoding::Varint(7), IntEncoding::Varint(128)] {
+        for variants in tables {
+            let options = SerdeOptions::new().ints(ints).len_group(3).variant_counts(variants);
+            let bytes = to_bytes_with(&frame, options).unwrap();
+            assert_eq!(from_bytes_with::<Frame>(&bytes, options), Ok(frame.clone()), "{:?}", ints);
+        }
+    }
+
+    // Порядок битов задается писателем и читателем.
+    let mut serializer = BitSerializer::new(BitWriter::with_order(Vec::new(), crate::BitOrder::Lsb0), SerdeOptions::new());
+    frame.serialize(&mut serializer).unwrap();
+    (-5i8).serialize(&mut serializer).unwrap();
+    let mut writer = serializer.into_inner();
+    writer.finish(Padding::PadOnes).unwrap();
+    let bytes = writer.into_inner();
+    let reader = BitReader::with_order(&bytes[..], crate::BitOrder::Lsb0);
+    let mut deserializer = BitDeserializer::new(reader, SerdeOptions::new());
+    assert_eq!(Frame::deserialize(&mut deserializer), Ok(frame.clone()));
+    assert_eq!(i8::deserialize(&mut deserializer), Ok(-5));
+    assert!(deserializer.get_ref().remaining().unwrap() < 8);
+}
+
+// Ширина полей: bool - 1 бит, номер варианта - ceil(log2(n)) бит, varint и длины.
+#[test]
+fn test2() {
+    use alloc::vec;
+
+    #[derive(serde::Serialize)]
+    enum Five {
+        A,
+        B,
+        C,
+        D,
+        E,
+    }
+
+    #[derive(serde::Serialize)]
+    enum One {
+        X(u8),
+    }
+
+    let options = SerdeOptions::new().variant_counts(&[("Five", 5), ("One", 1)]);
+    // 100 000 011
+    assert_eq!(to_bytes_with(&[Five::E, Five::A, Five::D], options), Ok(vec![0b_1000_0001, 0b_1000_0000]));
+    assert_eq!(to_bytes_with(&One::X(0xFF), options), Ok(vec![0xFF]));
+    // Без таблицы номер варианта записывается как длина: 0100 0, 0001 0, 0010 0.
+    assert_eq!(to_bytes(&(Five::E, Five::B, Five::C)), Ok(vec![0b_0100_0000, 0b_1000_1000]));
+
+    // 300 = 10 0101100: 0101100 1 0000010 0
+    let options = SerdeOptions::new().ints(IntEncoding::Varint(7));
+    assert_eq!(to_bytes_with(&300u64, options), Ok(vec![0b_0101_1001, 0b_0000_0100]));
+    // Длина 2 = 010 0, затем байты строки.
+    let options = SerdeOptions::new().len_group(3);
+    assert_eq!(to_bytes_with("hi", options), Ok(vec![0b_0100_0110, 0b_1000_0110, 0b_1001_0000]));
+    assert_eq!(to_bytes(&(Some(true), None::<bool>, true)), Ok(vec![0b_1101_0000]));
+}
+
+// Ошибки сериализации и некорректные данные.
+#[test]
+fn test3() {
+    use serde::{Deserialize, Serializer};
+
+    #[derive(serde::Serialize, Deserialize, Debug, PartialEq)]
+    enum Two {
+        A,
+        B,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(untagged)]
+    #[allow(dead_code)]
+    enum Untagged {
+        Int(u8),
+    }
+
+    let mut serializer = BitSerializer::new(BitWriter::new(Vec::new()), SerdeOptions::new());
+    let r = serializer.collect_seq([1u8, 2, 3].iter().filter(|&&v| v > 1));
+    assert_eq!(r, Err(SerdeError::LengthRequired));
+
+    let options = SerdeOptions::new().variant_counts(&[("Two", 1)]);
+    assert_eq!(to_bytes_with(&Two::B, options), Err(SerdeError::InvalidVariant { index: 1, count: 1 }));
+    let r = from_bytes_with::<Two>(&[0], options);
+    assert_eq!(r, Err(SerdeError::VariantCount { name: "Two", table: 1, variants: 2 }));
+    // Номер 2 (0010 0) при двух вариантах.
+    assert_eq!(from_bytes::<Two>(&[0b_0010_0000]), Err(SerdeError::InvalidVariant { index: 2, count: 2 }));
+    assert_eq!(from_bytes::<Two>(&[0b_0001_0000]), Ok(Two::B));
+
+    let options = SerdeOptions::new().ints(IntEncoding::Varint(7));
+    let bytes = to_bytes_with(&300u16, options).unwrap();
+    assert_eq!(from_bytes_with::<u8>(&bytes, options), Err(SerdeError::IntegerOverflow));
+    assert_eq!(from_bytes_with::<i16>(&bytes, options), Ok(150));
+    let options = SerdeOptions::new().ints(IntEncoding::Varint(0));
+    assert_eq!(to_bytes_with(&1u8, options), Err(SerdeError::InvalidGroup { bits: 0 }));
+
+    assert_eq!(from_bytes::<char>(&[0xFF, 0xFF, 0xF8]), Err(SerdeError::InvalidChar { value: 0x1F_FFFF }));
+    assert_eq!(from_bytes::<String>(&[0b_0001_0111, 0b_1111_1000]), Err(SerdeError::InvalidUtf8));
+    assert_eq!(from_bytes::<u8>(&[1, 2, 3]), Err(SerdeError::TrailingBytes { len: 2 }));
+    assert!(matches!(from_bytes::<u16>(&[1]), Err(SerdeError::Read(_))));
+    assert_eq!(from_bytes::<Untagged>(&[1]).unwrap_err(), SerdeError::AnyNotSupported);
+}
+
+// Одноименным перечислениям из разных модулей rename дает каждому свою
+// строку таблицы.
+#[test]
+fn test4() {
+    use serde::{Deserialize, Serialize};
+
+    mod a {
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        pub enum Kind {
+            X,
+            Y,
+        }
+    }
+
+    mod b {
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        #[serde(rename = "b::Kind")]
+        pub enum Kind {
+            X,
+            Y,
+            Z,
+            W,
+            V,
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Pair(a::Kind, b::Kind);
+
+    const OPTIONS: SerdeOptions = SerdeOptions::new().variant_counts(&[("Kind", 2), ("b::Kind", 5)]);
+    // 1 100 и 4 нулевых бита
+    let bytes = to_bytes_with(&Pair(a::Kind::Y, b::Kind::V), OPTIONS).unwrap();
+    assert_eq!(bytes, [0b_1100_0000]);
+    assert_eq!(from_bytes_with(&bytes, OPTIONS), Ok(Pair(a::Kind::Y, b::Kind::V)));
+}
+
+// Повторяющееся имя в таблице кол-ва вариантов.
+#[test]
+#[should_panic(expected = "duplicate enum name in variant_counts")]
+fn test5() {
+    let _ = SerdeOptions::new().variant_counts(&[("Kind", 2), ("Other", 3), ("Kind", 5)]);
+}
@@ -0,0 +1,537 @@
+//! Ошибки упаковки битовых последовательностей.
+
+use core::fmt;
+
+#[cfg(feature = "serde")]
+use alloc::string::{String, ToString};
+
+/// Ошибка [`repack`](crate::repack).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepackError {
+    /// Один из параметров `bits_in`, `bits_out`, `bits_limit` равен нулю.
+    ZeroWidth {
+        /// Запрошенное кол-во значащих бит во входном эл-те.
+        bits_in: usize,
+        /// Запрошенное кол-во значащих бит в выходном эл-те.
+        bits_out: usize,
+        /// Запрошенное ограничение кол-ва входных бит
+        /// (`usize::MAX` для потоковой упаковки без ограничения).
+        bits_limit: usize,
+    },
+    /// Кол-во значащих бит во входном эл-те превышает его размер.
+    BitsInTooLarge {
+        /// Запрошенное кол-во значащих бит.
+        bits_in: usize,
+        /// Размер входного эл-та в битах.
+        size: usize,
+    },
+    /// Кол-во значащих бит в выходном эл-те превышает его размер.
+    BitsOutTooLarge {
+        /// Запрошенное кол-во значащих бит.
+        bits_out: usize,
+        /// Размер выходного эл-та в битах.
+        size: usize,
+    },
+    /// Общее кол-во бит нельзя поровну разделить на выходные эл-ты
+    /// (или последнее поле шаблона в [`repack_pattern`](crate::repack_pattern) неполное).
+    UnalignedBitsLimit {
+        /// Запрошенное ограничение кол-ва входных бит с учетом dst_offset
+        /// (или кол-во бит, переданных в [`Repacker`](crate::Repacker)).
+        bits_limit: usize,
+        /// Кол-во значащих бит в выходном эл-те (неполном поле).
+        bits_out: usize,
+    },
+    /// Величину сдвига не удалось преобразовать в тип входного эл-та.
+    ///
+    /// Не возвращается: сдвиги выполняются над 128-битным представлением
+    /// [`Word`](crate::Word), а слишком широкие эл-ты дают [`RepackError::WidthTooLarge`].
+    /// Вариант сохранен для совместимости.
+    SrcShiftConversion {
+        /// Величина сдвига.
+        shift: usize,
+    },
+    /// Величину сдвига не удалось преобразовать в тип выходного эл-та.
+    ///
+    /// Не возвращается, как и [`RepackError::SrcShiftConversion`].
+    DstShiftConversion {
+        /// Величина сдвига.
+        shift: usize,
+    },
+    /// Бит входного эл-та не удалось преобразовать в тип выходного эл-та.
+    ///
+    /// Не возвращается: [`Word::from_raw`](crate::Word::from_raw) не может
+    /// завершиться ошибкой, а тип, не представляющий отдельные биты, отклоняется
+    /// до упаковки с [`RepackError::UnsupportedWord`]. Вариант сохранен для совместимости.
+    ValueConversion {
+        /// Индекс входного эл-та.
+        index: usize,
+        /// Значение бита (0 или 1).
+        value: u8,
+    },
+    /// Ширина эл-та больше 128 бит: биты переносятся через 128-битное
+    /// представление [`Word`](crate::Word), поэтому шире могут быть только
+    /// пользовательские реализации.
+    WidthTooLarge {
+        /// Запрошенная ширина (bits_in или bits_out).
+        bits: usize,
+    },
+    /// Тип эл-та не поддерживается: пользовательская реализация
+    /// [`Word`](crate::Word) теряет младший бит при преобразовании
+    /// (или, для [`RepackReader`](crate::RepackReader) и
+    /// [`RepackWriter`](crate::RepackWriter), не занимает целое число байтов не больше 16).
+    UnsupportedWord {
+        /// Размер типа в битах.
+        size: usize,
+    },
+    /// В выходном срезе недостаточно эл-тов для результата.
+    DstTooSmall {
+        /// Кол-во эл-тов в выходном срезе.
+        len: usize,
+        /// Необходимое кол-во эл-тов.
+        required: usize,
+    },
+    /// Во входном эл-те установлены биты выше bits_in (строгий режим).
+    ValueTooWide {
+        /// Индекс входного эл-та.
+        index: usize,
+        /// Значение эл-та (для значений `u128` больше `i128::MAX` - `i128::MAX`).
+        value: i128,
+        /// Кол-во значащих бит во входном эл-те.
+        bits_in: usize,
+    },
+    /// Входной эл-т не помещается в bits_in бит дополнительного кода (знаковый режим).
+    ValueOverflow {
+        /// Индекс входного эл-та.
+        index: usize,
+        /// Значение эл-та (для значений `u128` больше `i128::MAX` - `i128::MAX`).
+        value: i128,
+        /// Кол-во значащих бит во входном эл-те.
+        bits_in: usize,
+    },
+    /// Во входном срезе меньше бит, чем нужно (строгий режим).
+    SrcTooShort {
+        /// Кол-во бит во входном срезе.
+        available: usize,
+        /// Необходимое кол-во бит (src_offset + bits_limit).
+        required: usize,
+    },
+    /// Большое целое не помещается в заданное кол-во бит.
+    IntegerTooWide {
+        /// Заданное кол-во бит.
+        bits: usize,
+        /// Кол-во значащих бит числа.
+        required: usize,
+    },
+    /// Шаблон ширин полей пуст.
+    EmptyPattern,
+    /// Кол-во бит последовательности не помещается в `usize`.
+    LengthOverflow,
+}
+
+impl fmt::Display for RepackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            RepackError::ZeroWidth { bits_in, bits_out, bits_limit } => write!(
+                f,
+                "bits_in < 1 || bits_out < 1 || bits_limit < 1 (bits_in = {}, bits_out = {}, bits_limit = {})",
+                bits_in, bits_out, bits_limit
+            ),
+            RepackError::BitsInTooLarge { bits_in, size } => {
+                write!(f, "bits_in > T1::size (bits_in = {}, T1::size = {})", bits_in, size)
+            }
+            RepackError::BitsOutTooLarge { bits_out, size } => {
+                write!(f, "bits_out > T2::size (bits_out = {}, T2::size = {})", bits_out, size)
+            }
+            RepackError::UnalignedBitsLimit { bits_limit, bits_out } => write!(
+                f,
+                "bits_limit % bits_out != 0 (bits_limit = {}, bits_out = {})",
+                bits_limit, bits_out
+            ),
+            RepackError::SrcShiftConversion { shift } => {
+                write!(f, "can't convert usize to T1 (shift = {})", shift)
+            }
+            RepackError::DstShiftConversion { shift } => {
+                write!(f, "can't convert usize to T2 (shift = {})", shift)
+            }
+            RepackError::ValueConversion { index, value } => {
+                write!(f, "can't convert T1 to T2 (src[{}], bit value = {})", index, value)
+            }
+            RepackError::WidthTooLarge { bits } => write!(f, "bits > 128 (bits = {})", bits),
+            RepackError::UnsupportedWord { size } => write!(f, "unsupported element type (T::size = {})", size),
+            RepackError::DstTooSmall { len, required } => {
+                write!(f, "dst.len() < required (dst.len() = {}, required = {})", len, required)
+            }
+            RepackError::ValueTooWide { index, value, bits_in } => {
+                write!(f, "src[{}] = {} doesn't fit in bits_in = {}", index, value, bits_in)
+            }
+            RepackError::ValueOverflow { index, value, bits_in } => {
+                write!(f, "src[{}] = {} overflows signed bits_in = {}", index, value, bits_in)
+            }
+            RepackError::SrcTooShort { available, required } => write!(
+                f,
+                "src has fewer bits than required (available = {}, required = {})",
+                available, required
+            ),
+            RepackError::IntegerTooWide { bits, required } => {
+                write!(f, "integer doesn't fit in bits = {} (required = {})", bits, required)
+            }
+            RepackError::EmptyPattern => write!(f, "widths pattern is empty"),
+            RepackError::LengthOverflow => write!(f, "bit count overflows usize"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RepackError {}
+
+/// Ошибка [`BitReader`](crate::BitReader).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadError {
+    /// Поток закончился раньше, чем были прочитаны запрошенные биты.
+    UnexpectedEnd {
+        /// Номер бита потока, с которого начиналось чтение.
+        position: usize,
+        /// Запрошенное кол-во бит.
+        requested: usize,
+        /// Кол-во бит, оставшихся в потоке.
+        available: usize,
+    },
+    /// Запрошенное кол-во бит превышает размер типа результата или 128.
+    BitsTooLarge {
+        /// Запрошенное кол-во бит.
+        bits: usize,
+        /// Наибольшее допустимое кол-во бит: размер типа результата, но не больше 128.
+        size: usize,
+    },
+    /// Ошибка чтения из [`std::io::Read`].
+    #[cfg(feature = "std")]
+    Io(std::io::ErrorKind),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ReadError::UnexpectedEnd { position, requested, available } => write!(
+                f,
+                "unexpected end of stream at bit {} (requested = {}, available = {})",
+                position, requested, available
+            ),
+            ReadError::BitsTooLarge { bits, size } => {
+                write!(f, "bits > T::size (bits = {}, T::size = {})", bits, size)
+            }
+            #[cfg(feature = "std")]
+            ReadError::Io(kind) => write!(f, "I/O error: {}", kind),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ReadError {}
+
+/// Ошибка [`BitWriter`](crate::BitWriter).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteError {
+    /// В значении установлены биты выше запрошенного кол-ва бит.
+    ValueTooWide {
+        /// Значение (для значений `u128` больше `i128::MAX` - `i128::MAX`).
+        value: i128,
+        /// Запрошенное кол-во бит.
+        bits: usize,
+    },
+    /// Запрошенное кол-во бит превышает размер типа значения или 128.
+    BitsTooLarge {
+        /// Запрошенное кол-во бит.
+        bits: usize,
+        /// Наибольшее допустимое кол-во бит: размер типа значения, но не больше 128.
+        size: usize,
+    },
+    /// Поток заканчивается на середине байта, а дополнение запрещено
+    /// ([`Padding::Reject`](crate::Padding::Reject)).
+    Unaligned {
+        /// Кол-во записанных бит.
+        position: usize,
+    },
+    /// Ошибка записи в [`std::io::Write`].
+    #[cfg(feature = "std")]
+    Io(std::io::ErrorKind),
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            WriteError::ValueTooWide { value, bits } => {
+                write!(f, "value = {} doesn't fit in bits = {}", value, bits)
+            }
+            WriteError::BitsTooLarge { bits, size } => {
+                write!(f, "bits > T::size (bits = {}, T::size = {})", bits, size)
+            }
+            WriteError::Unaligned { position } => {
+                write!(f, "position % 8 != 0 (position = {})", position)
+            }
+            #[cfg(feature = "std")]
+            WriteError::Io(kind) => write!(f, "I/O error: {}", kind),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WriteError {}
+
+/// Ошибка [`BitLayout`](crate::BitLayout).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutError {
+    /// Ширина поля равна нулю или больше допустимой
+    /// (127 бит для беззнаковых полей, 128 - для знаковых).
+    InvalidWidth {
+        /// Номер поля.
+        index: usize,
+        /// Ширина поля.
+        bits: usize,
+    },
+    /// Поле выходит за пределы заданной длины записи.
+    OutOfBounds {
+        /// Номер поля.
+        index: usize,
+        /// Конец поля в битах.
+        end: usize,
+        /// Длина записи в битах.
+        len: usize,
+    },
+    /// Длина записи или конец поля больше [`BitLayout::MAX_LEN`](crate::BitLayout::MAX_LEN) бит.
+    TooLong {
+        /// Длина записи или конец поля в битах (`usize::MAX`, если он не помещается в usize).
+        len: usize,
+    },
+    /// Поля пересекаются.
+    Overlap {
+        /// Номер поля, которое начинается раньше.
+        first: usize,
+        /// Номер поля, которое начинается внутри первого.
+        second: usize,
+    },
+    /// Имя поля повторяется.
+    DuplicateName {
+        /// Номер поля с повторным именем.
+        index: usize,
+    },
+    /// Некорректное описание поля в текстовой раскладке.
+    Parse {
+        /// Номер описания поля (начиная с нуля).
+        token: usize,
+    },
+    /// Поля с таким именем нет в раскладке.
+    UnknownField,
+    /// Кол-во значений не совпадает с кол-вом полей.
+    ValueCount {
+        /// Кол-во значений.
+        len: usize,
+        /// Кол-во полей.
+        required: usize,
+    },
+    /// Значение не помещается в поле.
+    ValueOutOfRange {
+        /// Номер поля.
+        index: usize,
+        /// Значение.
+        value: i128,
+    },
+    /// Во входном срезе меньше байтов, чем в записи.
+    SrcTooShort {
+        /// Кол-во байтов во входном срезе.
+        len: usize,
+        /// Кол-во байтов записи.
+        required: usize,
+    },
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            LayoutError::InvalidWidth { index, bits } => write!(f, "field {} has invalid width {}", index, bits),
+            LayoutError::OutOfBounds { index, end, len } => {
+                write!(f, "field {} ends at bit {} beyond record length {}", index, end, len)
+            }
+            LayoutError::TooLong { len } => write!(f, "record length {} is too large", len),
+            LayoutError::Overlap { first, second } => write!(f, "fields {} and {} overlap", first, second),
+            LayoutError::DuplicateName { index } => write!(f, "field {} has a duplicate name", index),
+            LayoutError::Parse { token } => write!(f, "invalid field spec #{}", token),
+            LayoutError::UnknownField => write!(f, "unknown field name"),
+            LayoutError::ValueCount { len, required } => {
+                write!(f, "values.len() != fields (values.len() = {}, fields = {})", len, required)
+            }
+            LayoutError::ValueOutOfRange { index, value } => {
+                write!(f, "value = {} doesn't fit in field {}", value, index)
+            }
+            LayoutError::SrcTooShort { len, required } => {
+                write!(f, "src.len() < record length (src.len() = {}, required = {})", len, required)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LayoutError {}
+
+/// Ошибка проверки значения [`BitPack`](crate::BitPack) перед упаковкой.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackError {
+    /// Значение поля не помещается в его ширину и было бы усечено.
+    ValueOutOfRange {
+        /// Имя поля (номер для полей кортежной структуры).
+        field: &'static str,
+    },
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            PackError::ValueOutOfRange { field } => write!(f, "value of field {} doesn't fit in its bit width", field),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PackError {}
+
+/// Ошибка распаковки [`BitPack`](crate::BitPack).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnpackError {
+    /// Во входном срезе меньше байтов, чем занимает упакованное значение.
+    SrcTooShort {
+        /// Кол-во байтов во входном срезе.
+        len: usize,
+        /// Кол-во байтов упакованного значения.
+        required: usize,
+    },
+    /// Значение поля не соответствует ни одному варианту перечисления.
+    InvalidDiscriminant {
+        /// Значение поля.
+        value: u128,
+    },
+}
+
+impl fmt::Display for UnpackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            UnpackError::SrcTooShort { len, required } => {
+                write!(f, "src.len() < required (src.len() = {}, required = {})", len, required)
+            }
+            UnpackError::InvalidDiscriminant { value } => write!(f, "invalid enum discriminant {}", value),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnpackError {}
+
+/// Ошибка сериализации и десериализации в компактном битовом формате
+/// (см. [`BitSerializer`](crate::BitSerializer)).
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SerdeError {
+    /// Ошибка записи в битовый поток.
+    Write(WriteError),
+    /// Ошибка чтения из битового потока.
+    Read(ReadError),
+    /// Ошибка, сообщенная реализацией `Serialize` или `Deserialize`.
+    Message(String),
+    /// Длина последовательности или словаря неизвестна заранее.
+    LengthRequired,
+    /// Ширина группы бит varint равна нулю или больше 128.
+    InvalidGroup {
+        /// Ширина группы.
+        bits: usize,
+    },
+    /// Прочитанное значение не помещается в тип результата.
+    IntegerOverflow,
+    /// Прочитанный код не является символом Unicode.
+    InvalidChar {
+        /// Прочитанный код.
+        value: u32,
+    },
+    /// Прочитанная строка не является корректной UTF-8.
+    InvalidUtf8,
+    /// Номер варианта перечисления не меньше кол-ва вариантов.
+    InvalidVariant {
+        /// Номер варианта.
+        index: u128,
+        /// Кол-во вариантов.
+        count: usize,
+    },
+    /// Кол-во вариантов перечисления в таблице параметров не совпадает
+    /// с кол-вом вариантов типа.
+    VariantCount {
+        /// Имя перечисления.
+        name: &'static str,
+        /// Кол-во вариантов в таблице параметров.
+        table: usize,
+        /// Кол-во вариантов типа.
+        variants: usize,
+    },
+    /// Формат не хранит типы значений, поэтому `deserialize_any` и
+    /// `deserialize_ignored_any` не поддерживаются.
+    AnyNotSupported,
+    /// После значения во входных данных остались целые байты.
+    TrailingBytes {
+        /// Кол-во оставшихся байтов.
+        len: usize,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerdeError::Write(e) => write!(f, "{}", e),
+            SerdeError::Read(e) => write!(f, "{}", e),
+            SerdeError::Message(msg) => f.write_str(msg),
+            SerdeError::LengthRequired => write!(f, "sequence length is unknown"),
+            SerdeError::InvalidGroup { bits } => write!(f, "varint group < 1 || group > 128 (group = {})", bits),
+            SerdeError::IntegerOverflow => write!(f, "integer overflows the target type"),
+            SerdeError::InvalidChar { value } => write!(f, "invalid char code {:#x}", value),
+            SerdeError::InvalidUtf8 => write!(f, "invalid UTF-8 string"),
+            SerdeError::InvalidVariant { index, count } => {
+                write!(f, "variant index >= count (index = {}, count = {})", index, count)
+            }
+            SerdeError::VariantCount { name, table, variants } => write!(
+                f,
+                "enum {} has {} variants, but the table declares {}",
+                name, variants, table
+            ),
+            SerdeError::AnyNotSupported => write!(f, "deserialize_any is not supported by the bit format"),
+            SerdeError::TrailingBytes { len } => write!(f, "{} trailing bytes after the value", len),
+        }
+    }
+}
+
+// При включенном serde/std это std::error::Error.
+#[cfg(feature = "serde")]
+impl serde::de::StdError for SerdeError {}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<WriteError> for SerdeError {
+    fn from(e: WriteError) -> Self {
+        SerdeError::Write(e)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ReadError> for SerdeError {
+    fn from(e: ReadError) -> Self {
+        SerdeError::Read(e)
+    }
+}
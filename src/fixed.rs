@@ -0,0 +1,147 @@
+//! Упаковка с ширинами эл-тов, известными при компиляции.
+
+use core::marker::PhantomData;
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+use crate::raw::{mask, repack_words};
+use crate::{RepackError, RepackOptions, Word};
+
+// Проверка ширин, которая вычисляется при компиляции для каждой
+// использованной комбинации типов и ширин.
+struct Widths<T1, T2, const BITS_IN: usize, const BITS_OUT: usize>(PhantomData<(T1, T2)>);
+
+impl<T1: Word, T2: Word, const BITS_IN: usize, const BITS_OUT: usize> Widths<T1, T2, BITS_IN, BITS_OUT> {
+    const VALID: () = {
+        assert!(BITS_IN >= 1 && BITS_OUT >= 1, "BITS_IN < 1 || BITS_OUT < 1");
+        assert!(BITS_IN <= T1::BITS, "BITS_IN > T1::size");
+        assert!(BITS_OUT <= T2::BITS, "BITS_OUT > T2::size");
+    };
+}
+
+/// Кол-во эл-тов, которое [`repack_const`] вернет для входного среза из len эл-тов.
+///
+/// ```
+///     assert_eq!(bits_rs::repacked_len_const::<3, 2>(2), 3);
+///     assert_eq!(bits_rs::repacked_len_const::<3, 4>(3), 3);
+/// ```
+pub const fn repacked_len_const<const BITS_IN: usize, const BITS_OUT: usize>(len: usize) -> usize {
+    (len * BITS_IN).div_ceil(BITS_OUT)
+}
+
+/// Вариант [`repack`](crate::repack), в котором ширины эл-тов - параметры
+/// типа, а bits_limit равен src.len() * BITS_IN.
+///
+/// Недопустимые ширины (ноль, больше размера типа) не компилируются, а цикл
+/// упаковки собирается отдельно для каждой пары ширин. Если кол-во входных бит
+/// не делится на BITS_OUT, последний эл-т дополняется нулевыми битами, как в
+/// [`RepackExt`](crate::RepackExt).
+///
+/// # Examples
+///
+/// ```
+///     let src = [5u16, 5]; // [0b_101, 0b_101]
+///     let r: Vec<u8> = bits_rs::repack_const::<u16, u8, 3, 2>(&src);
+///     assert_eq!(r, [0b_10, 0b_11, 0b_01]);
+///     let r: Vec<u8> = bits_rs::repack_const::<u16, u8, 3, 4>(&src);
+///     assert_eq!(r, [0b_1011, 0b_0100]);
+/// ```
+///
+/// ```compile_fail
+///     // BITS_OUT больше размера u8.
+///     let r: Vec<u8> = bits_rs::repack_const::<u16, u8, 3, 9>(&[5u16]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn repack_const<T1, T2, const BITS_IN: usize, const BITS_OUT: usize>(src: &[T1]) -> Vec<T2>
+where
+    T1: Word,
+    T2: Word,
+{
+    let mut dst = vec![T2::from_raw(0); repacked_len_const::<BITS_IN, BITS_OUT>(src.len())];
+    repack_const_into::<T1, T2, BITS_IN, BITS_OUT>(src, &mut dst).expect("dst has the required length");
+    dst
+}
+
+/// То же, что и [`repack_const`], но результат записывается в начало dst.
+///
+/// Возвращает кол-во записанных эл-тов ([`repacked_len_const`]).
+///
+/// # Errors
+/// * [`RepackError::DstTooSmall`] - в dst меньше эл-тов, чем нужно.
+///
+/// # Examples
+///
+/// ```
+///     let mut dst = [0u16; 2];
+///     let n = bits_rs::repack_const_into::<u8, u16, 8, 12>(&[0x12, 0x34, 0x56], &mut dst);
+///     assert_eq!(n, Ok(2));
+///     assert_eq!(dst, [0x123, 0x456]);
+/// ```
+pub fn repack_const_into<T1, T2, const BITS_IN: usize, const BITS_OUT: usize>(
+    src: &[T1],
+    dst: &mut [T2],
+) -> Result<usize, RepackError>
+where
+    T1: Word,
+    T2: Word,
+{
+    let () = Widths::<T1, T2, BITS_IN, BITS_OUT>::VALID;
+
+    let len = repacked_len_const::<BITS_IN, BITS_OUT>(src.len());
+    if dst.len() < len {
+        return Err(RepackError::DstTooSmall { len: dst.len(), required: len });
+    }
+
+    if BITS_IN + BITS_OUT <= 128 {
+        // Тот же сдвиговый регистр, что и в raw::repack_words, но с
+        // ширинами-константами.
+        let mut acc = 0u128;
+        let mut acc_len = 0;
+        let mut src = src.iter();
+        for w in &mut dst[..len] {
+            while acc_len < BITS_OUT {
+                let v = src.next().map_or(0, |v| v.to_raw() & mask(BITS_IN));
+                acc = (acc << BITS_IN) | v;
+                acc_len += BITS_IN;
+            }
+            acc_len -= BITS_OUT;
+            *w = T2::from_raw((acc >> acc_len) & mask(BITS_OUT));
+        }
+    } else {
+        repack_words(src, BITS_IN, 0, &mut dst[..len], BITS_OUT, RepackOptions::new());
+    }
+
+    Ok(len)
+}
+
+// Результат совпадает с repack с дополнением последнего эл-та нулями.
+#[test]
+#[cfg(feature = "alloc")]
+fn test1() {
+    fn check<const BITS_IN: usize, const BITS_OUT: usize>(src: &[u128]) {
+        let options = crate::RepackOptions::new().padding(crate::Padding::PadZeros);
+        let e: Vec<u128> = crate::repack_with(src, BITS_IN, BITS_OUT, src.len() * BITS_IN, options).unwrap();
+        assert_eq!(repack_const::<u128, u128, BITS_IN, BITS_OUT>(src), e, "{} {}", BITS_IN, BITS_OUT);
+    }
+
+    let src: Vec<u128> = (0..37u128).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15_F39C_C060_5CED_C834)).collect();
+    check::<1, 1>(&src);
+    check::<3, 5>(&src);
+    check::<8, 12>(&src);
+    check::<13, 7>(&src);
+    check::<64, 64>(&src);
+    check::<100, 29>(&src);
+    check::<128, 8>(&src);
+    check::<7, 128>(&src);
+    check::<128, 128>(&src);
+    assert!(repack_const::<u8, u8, 3, 2>(&[]).is_empty());
+}
+
+// dst слишком мал.
+#[test]
+fn test2() {
+    let mut dst = [0u8; 2];
+    let r = repack_const_into::<u16, u8, 12, 8>(&[1, 2], &mut dst);
+    assert_eq!(r, Err(RepackError::DstTooSmall { len: 2, required: 3 }));
+}
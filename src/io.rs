@@ -0,0 +1,521 @@
+//! Упаковка битовых последовательностей при чтении и записи потоков `std::io`.
+
+use alloc::vec::Vec;
+use core::mem;
+use std::io::{self, Read, Write};
+
+use crate::raw::{word_from_bytes, word_to_bytes};
+use crate::{ByteOrder, Padding, RepackError, RepackOptions, Repacker, Word};
+
+/// Обертка над [`Write`]: принимает байты эл-тов T1 (по bits_in значащих бит
+/// в каждом) и записывает в inner байты эл-тов T2 (по bits_out значащих бит),
+/// как [`Repacker`].
+///
+/// Порядок байтов эл-тов во входном и выходном потоках задается
+/// [`RepackOptions::src_stream_byte_order`] и [`RepackOptions::dst_stream_byte_order`],
+/// а прочитанные эл-ты упаковываются с теми же параметрами, что и в [`Repacker`].
+/// Входные байты могут приходить порциями произвольной длины, в том числе
+/// разрезая эл-ты.
+///
+/// Неполный последний выходной эл-т записывается [`RepackWriter::finish`]
+/// согласно [`RepackOptions::padding`]. При [`Padding::Reject`] (по умолчанию)
+/// finish возвращает ошибку, если на целый выходной эл-т бит не хватило.
+///
+/// **Вызывайте finish (или [`RepackWriter::into_inner`]) явно.** При
+/// уничтожении обертки без него ошибки записи теряются, а неполный последний
+/// эл-т при [`Padding::Reject`] дополняется нулями, как при [`Padding::PadZeros`],
+/// чтобы не потерять его биты. Байты неполного входного эл-та при этом
+/// отбрасываются.
+///
+/// Размеры T1 и T2 должны быть целым числом байтов, не больше 16.
+///
+/// # Examples
+///
+/// ```
+///     use std::io::Write;
+///     use bits_rs::{ByteOrder, Padding, RepackOptions, RepackWriter};
+///     // 10-битные отсчеты, по одному в u16 (little-endian), пишутся плотно.
+///     let options = RepackOptions::new().src_stream_byte_order(ByteOrder::Little).padding(Padding::PadZeros);
+///     let mut writer = RepackWriter::<_, u16, u8>::with_options(Vec::new(), 10, 8, options).unwrap();
+///     writer.write_all(&[0xFF, 0x03, 0x01, 0x00]).unwrap(); // 0x3FF, 0x001
+///     let packed = writer.into_inner().unwrap();
+///     assert_eq!(packed, [0xFF, 0xC0, 0x10]);
+/// ```
+#[derive(Debug)]
+pub struct RepackWriter<W, T1, T2>
+where
+    W: Write,
+    T1: Word,
+    T2: Word,
+{
+    // None после into_inner.
+    inner: Option<W>,
+    // None после finish.
+    repacker: Option<Repacker<T1, T2>>,
+    options: RepackOptions,
+    // Байты неполного входного эл-та.
+    partial: Vec<u8>,
+    // Новое значение partial, которое принимается, только если вход упакован без ошибок.
+    scratch: Vec<u8>,
+    // Выходные байты, еще не записанные в inner.
+    out: Vec<u8>,
+    words_in: Vec<T1>,
+    words_out: Vec<T2>,
+}
+
+impl<W: Write, T1: Word, T2: Word> RepackWriter<W, T1, T2> {
+    /// Обертка с параметрами по умолчанию.
+    ///
+    /// # Errors
+    /// Те же, что и у [`Repacker::new`], а также
+    /// * [`RepackError::UnsupportedWord`] - размер T1 или T2 не кратен 8 или больше 128 бит.
+    pub fn new(inner: W, bits_in: usize, bits_out: usize) -> Result<Self, RepackError> {
+        Self::with_options(inner, bits_in, bits_out, RepackOptions::new())
+    }
+
+    /// То же, что и [`RepackWriter::new`], но с дополнительными параметрами упаковки.
+    pub fn with_options(inner: W, bits_in: usize, bits_out: usize, options: RepackOptions) -> Result<Self, RepackError> {
+        check_word::<T1>()?;
+        check_word::<T2>()?;
+        Ok(RepackWriter {
+            inner: Some(inner),
+            repacker: Some(Repacker::with_options(bits_in, bits_out, options)?),
+            options,
+            partial: Vec::new(),
+            scratch: Vec::new(),
+            out: Vec::new(),
+            words_in: Vec::new(),
+            words_out: Vec::new(),
+        })
+    }
+
+    /// Исходный поток.
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().expect("inner is taken only by into_inner")
+    }
+
+    /// Завершает упаковку: записывает неполный последний эл-т согласно
+    /// [`RepackOptions::padding`] и сбрасывает буферы inner.
+    /// Повторные вызовы после успешного завершения только сбрасывают буферы,
+    /// а после ошибки повторяют ее.
+    ///
+    /// # Errors
+    /// * [`io::ErrorKind::InvalidInput`] - последний входной эл-т записан не полностью.
+    /// * [`io::ErrorKind::InvalidData`] - [`RepackError::UnalignedBitsLimit`] при [`Padding::Reject`](crate::Padding::Reject).
+    /// * Ошибки записи в inner.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.repacker.is_some() {
+            if !self.partial.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "incomplete input element"));
+            }
+            // Упаковщик остается на месте, пока хвост не записан: повторный
+            // вызов после ошибки вернет ту же ошибку.
+            let repacker = self.repacker.clone().expect("checked above");
+            self.words_out.clear();
+            repacker
+                .finish(self.options.get_padding(), &mut self.words_out)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            self.repacker = None;
+            encode(&self.words_out, self.options.get_dst_stream_byte_order(), &mut self.out);
+        }
+        self.flush()
+    }
+
+    /// Завершает упаковку (см. [`RepackWriter::finish`]) и возвращает исходный поток.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.finish()?;
+        Ok(self.inner.take().expect("inner is taken only by into_inner"))
+    }
+
+    // Записывает в inner накопленные выходные байты.
+    fn drain(&mut self) -> io::Result<()> {
+        let inner = self.inner.as_mut().expect("inner is taken only by into_inner");
+        let mut written = 0;
+        let r = loop {
+            if written == self.out.len() {
+                break Ok(());
+            }
+            match inner.write(&self.out[written..]) {
+                Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.out.drain(..written);
+        r
+    }
+}
+
+impl<W: Write, T1: Word, T2: Word> Write for RepackWriter<W, T1, T2> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.drain()?;
+        let Some(repacker) = self.repacker.as_mut() else {
+            return Err(io::Error::other("write after finish"));
+        };
+
+        // При ошибке вход не принят: partial остается прежним, и запись можно повторить.
+        self.words_in.clear();
+        self.scratch.clone_from(&self.partial);
+        decode(&mut self.scratch, buf, self.options.get_src_stream_byte_order(), &mut self.words_in);
+        self.words_out.clear();
+        repacker
+            .push(&self.words_in, &mut self.words_out)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        mem::swap(&mut self.partial, &mut self.scratch);
+        encode(&self.words_out, self.options.get_dst_stream_byte_order(), &mut self.out);
+
+        // Вход уже принят, поэтому ошибка записи будет возвращена следующим вызовом.
+        let _ = self.drain();
+        Ok(buf.len())
+    }
+
+    /// Записывает в inner все готовые выходные эл-ты. Неполный последний
+    /// эл-т не записывается (см. [`RepackWriter::finish`]).
+    fn flush(&mut self) -> io::Result<()> {
+        self.drain()?;
+        self.inner.as_mut().expect("inner is taken only by into_inner").flush()
+    }
+}
+
+impl<W: Write, T1: Word, T2: Word> Drop for RepackWriter<W, T1, T2> {
+    fn drop(&mut self) {
+        if self.inner.is_some() && self.finish().is_err() && self.repacker.is_some() && self.partial.is_empty() {
+            // Хвост отклонен политикой Reject: вернуть ошибку некуда, поэтому
+            // его биты записываются с нулевым дополнением.
+            self.options = self.options.padding(Padding::PadZeros);
+            let _ = self.finish();
+        }
+    }
+}
+
+/// Обертка над [`Read`]: читает из inner байты эл-тов T1 (по bits_in значащих
+/// бит в каждом) и выдает байты эл-тов T2 (по bits_out значащих бит),
+/// как [`Repacker`].
+///
+/// Порядок байтов эл-тов задается так же, как у [`RepackWriter`]. Когда inner
+/// заканчивается, неполный последний выходной эл-т выдается согласно
+/// [`RepackOptions::padding`]. Чтобы отбросить биты, дополнившие поток до
+/// целого байта при записи, используйте [`Padding::Truncate`].
+///
+/// Размеры T1 и T2 должны быть целым числом байтов, не больше 16.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{ByteOrder, Padding, RepackOptions, RepackReader};
+///     let packed = [0xFFu8, 0xC0, 0x10];
+///     let options = RepackOptions::new().dst_stream_byte_order(ByteOrder::Little).padding(Padding::Truncate);
+///     let mut reader = RepackReader::<_, u8, u16>::with_options(&packed[..], 8, 10, options).unwrap();
+///     let mut samples = [0u16; 4];
+///     assert_eq!(reader.read_words(&mut samples).unwrap(), 2);
+///     assert_eq!(samples[..2], [0x3FF, 0x001]);
+/// ```
+#[derive(Debug)]
+pub struct RepackReader<R, T1, T2> {
+    inner: R,
+    // None, когда inner закончился.
+    repacker: Option<Repacker<T1, T2>>,
+    options: RepackOptions,
+    // Байты неполного входного эл-та.
+    partial: Vec<u8>,
+    // Новое значение partial, которое принимается, только если вход упакован без ошибок.
+    scratch: Vec<u8>,
+    // Выходные байты и позиция первого еще не выданного.
+    out: Vec<u8>,
+    out_pos: usize,
+    words_in: Vec<T1>,
+    words_out: Vec<T2>,
+}
+
+impl<R: Read, T1: Word, T2: Word> RepackReader<R, T1, T2> {
+    /// Обертка с параметрами по умолчанию.
+    ///
+    /// # Errors
+    /// Те же, что и у [`RepackWriter::new`].
+    pub fn new(inner: R, bits_in: usize, bits_out: usize) -> Result<Self, RepackError> {
+        Self::with_options(inner, bits_in, bits_out, RepackOptions::new())
+    }
+
+    /// То же, что и [`RepackReader::new`], но с дополнительными параметрами упаковки.
+    pub fn with_options(inner: R, bits_in: usize, bits_out: usize, options: RepackOptions) -> Result<Self, RepackError> {
+        check_word::<T1>()?;
+        check_word::<T2>()?;
+        Ok(RepackReader {
+            inner,
+            repacker: Some(Repacker::with_options(bits_in, bits_out, options)?),
+            options,
+            partial: Vec::new(),
+            scratch: Vec::new(),
+            out: Vec::new(),
+            out_pos: 0,
+            words_in: Vec::new(),
+            words_out: Vec::new(),
+        })
+    }
+
+    /// Исходный поток.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Исходный поток. Прочитанные из него, но еще не выданные данные теряются.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Читает в dst выходные эл-ты, пока dst не заполнится или поток не
+    /// закончится. Возвращает кол-во прочитанных эл-тов.
+    ///
+    /// # Errors
+    /// * [`io::ErrorKind::UnexpectedEof`] - поток закончился на середине эл-та.
+    /// * Ошибки, возвращаемые [`Read::read`].
+    pub fn read_words(&mut self, dst: &mut [T2]) -> io::Result<usize> {
+        let size = T2::BITS / 8;
+        let mut bytes = [0u8; 16];
+        for (count, w) in dst.iter_mut().enumerate() {
+            self.fill()?;
+            if self.out_pos == self.out.len() {
+                return Ok(count);
+            }
+            self.read_exact(&mut bytes[..size])?;
+            *w = word_from_bytes(&bytes[..size], self.options.get_dst_stream_byte_order());
+        }
+        Ok(dst.len())
+    }
+
+    // Читает inner, пока не появятся выходные байты или inner не закончится.
+    fn fill(&mut self) -> io::Result<()> {
+        let mut buf = [0u8; 1024];
+        while self.out_pos == self.out.len() {
+            let Some(repacker) = self.repacker.as_mut() else {
+                return Ok(());
+            };
+            let n = match self.inner.read(&mut buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            self.words_out.clear();
+            if n == 0 {
+                if !self.partial.is_empty() {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "incomplete input element"));
+                }
+                repacker
+                    .clone()
+                    .finish(self.options.get_padding(), &mut self.words_out)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                self.repacker = None;
+            } else {
+                self.words_in.clear();
+                self.scratch.clone_from(&self.partial);
+                decode(&mut self.scratch, &buf[..n], self.options.get_src_stream_byte_order(), &mut self.words_in);
+                repacker
+                    .push(&self.words_in, &mut self.words_out)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                mem::swap(&mut self.partial, &mut self.scratch);
+            }
+            self.out.clear();
+            self.out_pos = 0;
+            encode(&self.words_out, self.options.get_dst_stream_byte_order(), &mut self.out);
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read, T1: Word, T2: Word> Read for RepackReader<R, T1, T2> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.fill()?;
+        let n = buf.len().min(self.out.len() - self.out_pos);
+        buf[..n].copy_from_slice(&self.out[self.out_pos..self.out_pos + n]);
+        self.out_pos += n;
+        Ok(n)
+    }
+}
+
+// Эл-ты передаются в потоке целыми байтами.
+fn check_word<T: Word>() -> Result<(), RepackError> {
+    if T::BITS % 8 != 0 || T::BITS > 128 {
+        return Err(RepackError::UnsupportedWord { size: T::BITS });
+    }
+    Ok(())
+}
+
+// Добавляет в dst эл-ты из байтов partial и bytes. Байты неполного
+// последнего эл-та остаются в partial.
+fn decode<T: Word>(partial: &mut Vec<u8>, mut bytes: &[u8], order: ByteOrder, dst: &mut Vec<T>) {
+    let size = T::BITS / 8;
+    if !partial.is_empty() {
+        let take = (size - partial.len()).min(bytes.len());
+        partial.extend_from_slice(&bytes[..take]);
+        bytes = &bytes[take..];
+        if partial.len() < size {
+            return;
+        }
+        dst.push(word_from_bytes(partial, order));
+        partial.clear();
+    }
+    let chunks = bytes.chunks_exact(size);
+    partial.extend_from_slice(chunks.remainder());
+    dst.extend(chunks.map(|c| word_from_bytes::<T>(c, order)));
+}
+
+// Добавляет в dst байты эл-тов src.
+fn encode<T: Word>(src: &[T], order: ByteOrder, dst: &mut Vec<u8>) {
+    for &w in src {
+        dst.extend(word_to_bytes(w, order));
+    }
+}
+
+// Запись по одному байту и чтение обратно дают исходные отсчеты.
+#[test]
+fn test1() {
+    let samples: Vec<u16> = (0..101u16).map(|i| i.wrapping_mul(40503) & 0x3FF).collect();
+    let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+
+    let options = RepackOptions::new().src_stream_byte_order(ByteOrder::Little).padding(Padding::PadZeros);
+    let mut writer = RepackWriter::<_, u16, u8>::with_options(Vec::new(), 10, 8, options).unwrap();
+    for b in &bytes {
+        writer.write_all(core::slice::from_ref(b)).unwrap();
+    }
+    let packed = writer.into_inner().unwrap();
+    let expected: Vec<u8> = crate::repack_with(&samples, 10, 8, 1010, options).unwrap();
+    assert_eq!(packed, expected);
+
+    let options = RepackOptions::new().dst_stream_byte_order(ByteOrder::Little).padding(Padding::Truncate);
+    let mut reader = RepackReader::<_, u8, u16>::with_options(&packed[..], 8, 10, options).unwrap();
+    let mut unpacked = Vec::new();
+    reader.read_to_end(&mut unpacked).unwrap();
+    assert_eq!(unpacked, bytes);
+}
+
+// Хвост записывается при уничтожении обертки, ошибки неполных данных.
+#[test]
+fn test2() {
+    let mut packed = Vec::new();
+    {
+        let options = RepackOptions::new().padding(Padding::PadOnes);
+        let mut writer = RepackWriter::<_, u8, u16>::with_options(&mut packed, 4, 16, options).unwrap();
+        writer.write_all(&[0xA, 0xB, 0xC]).unwrap();
+        writer.flush().unwrap();
+        assert!(writer.get_ref().is_empty());
+    }
+    assert_eq!(packed, [0xAB, 0xCF]);
+
+    let mut writer = RepackWriter::<_, u16, u8>::new(Vec::new(), 12, 8).unwrap();
+    writer.write_all(&[0x01, 0x23, 0x04]).unwrap();
+    assert_eq!(writer.finish().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    writer.write_all(&[0x56]).unwrap();
+    assert_eq!(writer.into_inner().unwrap(), [0x12, 0x34, 0x56]);
+
+    let mut writer = RepackWriter::<_, u16, u8>::new(Vec::new(), 12, 8).unwrap();
+    writer.write_all(&[0x01, 0x23]).unwrap();
+    assert_eq!(writer.finish().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let mut reader = RepackReader::<_, u16, u8>::new(&[0x01u8, 0x23, 0x04][..], 12, 4).unwrap();
+    let mut out = Vec::new();
+    assert_eq!(reader.read_to_end(&mut out).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(out, [1, 2, 3]);
+}
+
+// Порядок байтов эл-тов в последовательности и в потоке задаются независимо.
+#[test]
+fn test3() {
+    let samples: Vec<u32> = (0..40u32).map(|i| i.wrapping_mul(2_654_435_761) >> 12).collect();
+    let options = RepackOptions::new()
+        .src_byte_order(ByteOrder::Little)
+        .dst_byte_order(ByteOrder::Little)
+        .src_stream_byte_order(ByteOrder::Little)
+        .dst_stream_byte_order(ByteOrder::Big)
+        .padding(Padding::PadZeros);
+    let words: Vec<u16> = crate::repack_with(&samples, 20, 13, 40 * 20, options).unwrap();
+    let expected: Vec<u8> = words.iter().flat_map(|w| w.to_be_bytes()).collect();
+
+    let mut writer = RepackWriter::<_, u32, u16>::with_options(Vec::new(), 20, 13, options).unwrap();
+    writer.write_all(&samples.iter().flat_map(|s| s.to_le_bytes()).collect::<Vec<_>>()).unwrap();
+    assert_eq!(writer.into_inner().unwrap(), expected);
+
+    let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+    let mut reader = RepackReader::<_, u32, u16>::with_options(&bytes[..], 20, 13, options).unwrap();
+    let mut out = Vec::new();
+    reader.read_to_end(&mut out).unwrap();
+    assert_eq!(out, expected);
+}
+
+// Ошибка Padding::Reject не теряется при повторных вызовах finish и read.
+#[test]
+fn test4() {
+    let options = RepackOptions::new().padding(Padding::Reject);
+    let mut writer = RepackWriter::<_, u8, u8>::with_options(Vec::new(), 3, 8, options).unwrap();
+    writer.write_all(&[1, 2, 3]).unwrap();
+    assert_eq!(writer.finish().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(writer.finish().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(writer.into_inner().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let mut reader = RepackReader::<_, u8, u8>::with_options(&[1u8, 2, 3][..], 3, 8, options).unwrap();
+    let mut out = Vec::new();
+    assert_eq!(reader.read_to_end(&mut out).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(out, [0x29]);
+    assert_eq!(reader.read(&mut [0u8; 4]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+// Эл-ты, не занимающие целого числа байтов, отклоняются при создании обертки.
+#[test]
+fn test5() {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Sample(u16);
+
+    impl Word for Sample {
+        const BITS: usize = 12;
+        const SIGNED: bool = false;
+
+        fn to_raw(self) -> u128 {
+            self.0 as u128
+        }
+
+        fn from_raw(raw: u128) -> Self {
+            Sample(raw as u16 & 0xFFF)
+        }
+    }
+
+    let r = RepackWriter::<_, Sample, u8>::new(Vec::new(), 12, 8);
+    assert_eq!(r.err(), Some(RepackError::UnsupportedWord { size: 12 }));
+    let r = RepackReader::<_, u8, Sample>::new(&[0u8][..], 8, 12);
+    assert_eq!(r.err(), Some(RepackError::UnsupportedWord { size: 12 }));
+}
+
+// После ошибки строгой проверки вход не принят, и запись можно продолжить.
+#[test]
+fn test6() {
+    let options = RepackOptions::new().strict_values(true).src_stream_byte_order(ByteOrder::Little);
+    let mut writer = RepackWriter::<_, u16, u8>::with_options(Vec::new(), 12, 8, options).unwrap();
+    writer.write_all(&[0x23]).unwrap();
+    // 0x1123 шире 12 бит.
+    assert_eq!(writer.write(&[0x11, 0x56]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    writer.write_all(&[0x01, 0x56, 0x04]).unwrap();
+    assert_eq!(writer.into_inner().unwrap(), [0x12, 0x34, 0x56]);
+
+    let mut reader = RepackReader::<_, u16, u8>::with_options(&[0x23u8, 0x11][..], 12, 8, options).unwrap();
+    assert_eq!(reader.read(&mut [0u8; 4]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+// При уничтожении без finish неполный хвост записывается с нулевым дополнением.
+#[test]
+fn test7() {
+    let mut packed = Vec::new();
+    {
+        let mut writer = RepackWriter::<_, u8, u8>::new(&mut packed, 3, 8).unwrap();
+        writer.write_all(&[1, 2, 3]).unwrap();
+    }
+    // 001 010 01 | 1 и 7 нулевых бит
+    assert_eq!(packed, [0b_0010_1001, 0b_1000_0000]);
+
+    let mut packed = Vec::new();
+    {
+        let options = RepackOptions::new().padding(Padding::PadOnes);
+        let mut writer = RepackWriter::<_, u8, u8>::with_options(&mut packed, 3, 8, options).unwrap();
+        writer.write_all(&[1, 2, 3]).unwrap();
+    }
+    assert_eq!(packed, [0b_0010_1001, 0b_1111_1111]);
+}
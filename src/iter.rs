@@ -0,0 +1,312 @@
+//! Упаковка битовой последовательности из итератора.
+
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+
+#[cfg(all(test, feature = "alloc"))]
+use alloc::vec::Vec;
+
+use crate::raw::{pad, shr, sign_extend, store_ordered, BitSource};
+use crate::{check_value, validate_widths, Padding, RepackError, RepackOptions, Word};
+
+/// Расширение итераторов целых чисел: ленивый вариант [`repack`](crate::repack).
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::RepackExt;
+///     let src = [5u16, 5]; // [0b_101, 0b_101]
+///     let r: Vec<u8> = src.iter().copied().repack(3, 2).unwrap().collect();
+///     assert_eq!(r, [0b_10, 0b_11, 0b_01]);
+/// ```
+pub trait RepackExt: Iterator + Sized
+where
+    Self::Item: Word,
+{
+    /// Итератор по эл-там, в каждом из которых bits_out значащих бит, из
+    /// битовой последовательности, по bits_in бит из каждого эл-та self.
+    ///
+    /// Выходные эл-ты вычисляются по мере запроса. Если кол-во входных бит
+    /// не делится на bits_out, последний эл-т дополняется нулевыми битами
+    /// ([`Padding::PadZeros`]).
+    ///
+    /// # Errors
+    /// * [`RepackError::ZeroWidth`] - bits_in или bits_out равен нулю.
+    /// * [`RepackError::BitsInTooLarge`] - bits_in больше размера Self::Item.
+    /// * [`RepackError::BitsOutTooLarge`] - bits_out больше размера T2.
+    fn repack<T2: Word>(self, bits_in: usize, bits_out: usize) -> Result<RepackIter<Self, T2>, RepackError> {
+        self.repack_with(bits_in, bits_out, RepackOptions::new().padding(Padding::PadZeros))
+    }
+
+    /// То же, что и [`RepackExt::repack`], но с дополнительными параметрами упаковки.
+    ///
+    /// Неполный последний эл-т выдается согласно [`RepackOptions::padding`].
+    /// При [`Padding::Reject`] (по умолчанию) итерация заканчивается на последнем
+    /// целом эл-те, а ошибку возвращает [`RepackIter::error`].
+    ///
+    /// Входные эл-ты проверяются по мере чтения, как в [`repack_with`](crate::repack_with)
+    /// ([`RepackOptions::strict_values`], [`RepackOptions::signed`]). На первом
+    /// эл-те, не прошедшем проверку, итерация заканчивается без выходного эл-та,
+    /// в который попали бы его биты, а ошибку возвращает [`RepackIter::error`].
+    /// Знак выходных эл-тов расширяется, как в [`repack_with`](crate::repack_with).
+    fn repack_with<T2: Word>(
+        self,
+        bits_in: usize,
+        bits_out: usize,
+        options: RepackOptions,
+    ) -> Result<RepackIter<Self, T2>, RepackError> {
+        validate_widths::<Self::Item, T2>(bits_in, bits_out, usize::MAX)?;
+        Ok(RepackIter {
+            source: BitSource::new(Checked::new(self, bits_in, options), bits_in, options.get_src_order())
+                .bytes(options.get_src_byte_order()),
+            bits_out,
+            options,
+            bits: 0,
+            error: None,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<I> RepackExt for I
+where
+    I: Iterator,
+    I::Item: Word,
+{
+}
+
+// Входные эл-ты с проверкой в строгом и знаковом режимах. После первой
+// ошибки эл-ты не выдаются.
+#[derive(Debug, Clone)]
+struct Checked<I> {
+    iter: I,
+    bits_in: usize,
+    options: RepackOptions,
+    // Индекс следующего эл-та.
+    index: usize,
+    error: Option<RepackError>,
+}
+
+impl<I> Checked<I> {
+    fn new(iter: I, bits_in: usize, options: RepackOptions) -> Self {
+        Checked { iter, bits_in, options, index: 0, error: None }
+    }
+}
+
+impl<I> Iterator for Checked<I>
+where
+    I: Iterator,
+    I::Item: Word,
+{
+    type Item = I::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<I::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+        let v = self.iter.next()?;
+        if let Err(e) = check_value(self.index, v, self.bits_in, self.options) {
+            self.error = Some(e);
+            return None;
+        }
+        self.index += 1;
+        Some(v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.error {
+            Some(_) => (0, Some(0)),
+            None => self.iter.size_hint(),
+        }
+    }
+}
+
+/// Итератор, возвращаемый [`RepackExt::repack`].
+///
+/// [`Iterator::size_hint`] (и [`ExactSizeIterator::len`]) не учитывает
+/// проверок [`RepackOptions::strict_values`] и [`RepackOptions::signed`]:
+/// если входной эл-т их не проходит, итерация заканчивается раньше.
+#[derive(Debug, Clone)]
+pub struct RepackIter<I, T2> {
+    source: BitSource<Checked<I>>,
+    bits_out: usize,
+    options: RepackOptions,
+    // Кол-во прочитанных входных бит.
+    bits: usize,
+    error: Option<RepackError>,
+    _marker: PhantomData<fn() -> T2>,
+}
+
+impl<I, T2> RepackIter<I, T2> {
+    /// Ошибка упаковки, из-за которой закончилась итерация:
+    /// * [`RepackError::UnalignedBitsLimit`] - при [`Padding::Reject`] на
+    ///   последний выходной эл-т не хватило бит;
+    /// * [`RepackError::ValueTooWide`], [`RepackError::ValueOverflow`] - входной
+    ///   эл-т не прошел проверку строгого или знакового режима.
+    ///
+    /// Известна только после окончания итерации.
+    pub fn error(&self) -> Option<RepackError> {
+        self.error
+    }
+}
+
+impl<I, T2> Iterator for RepackIter<I, T2>
+where
+    I: Iterator,
+    I::Item: Word,
+    T2: Word,
+{
+    type Item = T2;
+
+    fn next(&mut self) -> Option<T2> {
+        let bits_out = self.bits_out;
+        let (order, bytes) = (self.options.get_dst_order(), self.options.get_dst_byte_order());
+        let (seq, n) = self.source.read(bits_out);
+        self.bits = self.bits.saturating_add(n);
+        if let Some(e) = self.source.get_ref().error {
+            self.error = Some(e);
+            return None;
+        }
+        let seq = match n {
+            0 => return None,
+            _ if n == bits_out => seq,
+            _ => {
+                let padding = self.options.get_padding();
+                let padded = pad(shr(seq, bits_out - n), n, bits_out, padding, order, bytes);
+                if padded.is_none() && padding == Padding::Reject {
+                    self.error = Some(RepackError::UnalignedBitsLimit { bits_limit: self.bits, bits_out });
+                }
+                padded?
+            }
+        };
+        let w: T2 = store_ordered(seq, bits_out, order, bytes);
+        if self.options.get_signed() && T2::SIGNED && bits_out < T2::BITS {
+            return Some(T2::from_raw(sign_extend(w.to_raw(), bits_out)));
+        }
+        Some(w)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.source.bits_hint();
+        match self.options.get_padding() {
+            Padding::Reject | Padding::Truncate => (lo / self.bits_out, hi.map(|hi| hi / self.bits_out)),
+            _ => (lo.div_ceil(self.bits_out), hi.map(|hi| hi.div_ceil(self.bits_out))),
+        }
+    }
+}
+
+impl<I, T2> ExactSizeIterator for RepackIter<I, T2>
+where
+    I: ExactSizeIterator,
+    I::Item: Word,
+    T2: Word,
+{
+}
+
+impl<I, T2> FusedIterator for RepackIter<I, T2>
+where
+    I: FusedIterator,
+    I::Item: Word,
+    T2: Word,
+{
+}
+
+// Результат совпадает с repack, size_hint точен для ExactSizeIterator.
+#[test]
+#[cfg(feature = "alloc")]
+fn test1() {
+    let src: Vec<u16> = (0..100u16).map(|i| i.wrapping_mul(40503)).collect();
+    let expected: Vec<u8> = crate::repack(&src, 12, 8, 1200).unwrap();
+    let mut iter = src.iter().copied().repack::<u8>(12, 8).unwrap();
+    assert_eq!(iter.len(), 150);
+    iter.next();
+    assert_eq!(iter.len(), 149);
+    assert_eq!(iter.collect::<Vec<_>>()[..], expected[1..]);
+}
+
+// Неполный последний эл-т дополняется нулями.
+#[test]
+#[cfg(feature = "alloc")]
+fn test2() {
+    let options = RepackOptions::new().src_order(crate::BitOrder::Lsb0).padding(Padding::PadZeros);
+    let iter = [0b_0111u8, 0b_0001].into_iter().repack_with::<u8>(4, 3, options).unwrap();
+    assert_eq!(iter.len(), 3);
+    assert_eq!(iter.collect::<Vec<_>>(), [0b_111, 0b_010, 0b_000]);
+}
+
+// Ширины проверяются так же, как в repack.
+#[test]
+fn test3() {
+    let r = [1u8].into_iter().repack::<u8>(8, 0);
+    assert_eq!(r.err(), Some(RepackError::ZeroWidth { bits_in: 8, bits_out: 0, bits_limit: usize::MAX }));
+}
+
+// Порядок байтов эл-тов учитывается так же, как в repack_with.
+#[test]
+#[cfg(feature = "alloc")]
+fn test4() {
+    use crate::ByteOrder;
+
+    let src: Vec<u32> = (0..52u32).map(|i| i.wrapping_mul(2_654_435_761) >> 12).collect();
+    let orders = [
+        (ByteOrder::Little, ByteOrder::Big),
+        (ByteOrder::Big, ByteOrder::Little),
+        (ByteOrder::Little, ByteOrder::Little),
+    ];
+    for (src_bytes, dst_bytes) in orders {
+        let options = RepackOptions::new().src_byte_order(src_bytes).dst_byte_order(dst_bytes);
+        let expected: Vec<u16> = crate::repack_with(&src, 20, 13, 52 * 20, options).unwrap();
+        let r: Vec<u16> = src.iter().copied().repack_with(20, 13, options).unwrap().collect();
+        assert_eq!(r, expected, "{:?} {:?}", src_bytes, dst_bytes);
+    }
+}
+
+// Неполный последний эл-т выдается так же, как в repack_with.
+#[test]
+#[cfg(feature = "alloc")]
+fn test5() {
+    let src: Vec<u16> = (0..37u16).map(|i| i.wrapping_mul(40503)).collect();
+    let paddings = [Padding::Reject, Padding::PadZeros, Padding::PadOnes, Padding::PadWith(0x5A5A), Padding::Truncate];
+    for padding in paddings {
+        let options = RepackOptions::new().padding(padding).dst_order(crate::BitOrder::Lsb0);
+        let expected = crate::repack_with::<u16, u16>(&src, 13, 11, 37 * 13, options);
+        let mut iter = src.iter().copied().repack_with::<u16>(13, 11, options).unwrap();
+        let len = iter.len();
+        let r: Vec<u16> = iter.by_ref().collect();
+        assert_eq!(r.len(), len, "{:?}", padding);
+        match expected {
+            Ok(expected) => assert_eq!((r, iter.error()), (expected, None), "{:?}", padding),
+            Err(e) => {
+                assert_eq!(r.len(), 37 * 13 / 11);
+                assert_eq!(iter.error(), Some(e));
+            }
+        }
+    }
+}
+
+// Строгий и знаковый режимы: проверка входных эл-тов и расширение знака,
+// как в repack_with.
+#[test]
+#[cfg(feature = "alloc")]
+fn test6() {
+    let options = RepackOptions::new().strict_values(true);
+    let mut iter = [1u8, 2, 9, 3].into_iter().repack_with::<u8>(3, 2, options).unwrap();
+    // 001 010: эл-ты до src[2] выдаются, дальше итерация заканчивается.
+    assert_eq!(iter.by_ref().collect::<Vec<_>>(), [0b_00, 0b_10, 0b_10]);
+    let e = crate::repack_with::<u8, u8>(&[1, 2, 9, 3], 3, 2, 12, options).unwrap_err();
+    assert_eq!(iter.error(), Some(e));
+    assert_eq!(e, RepackError::ValueTooWide { index: 2, value: 9, bits_in: 3 });
+    assert_eq!(iter.next(), None);
+
+    let options = RepackOptions::new().signed(true);
+    let src = [-3i8, 2, -4, 1, 0, -1];
+    let expected: Vec<i8> = crate::repack_with(&src, 3, 6, 18, options).unwrap();
+    let r: Vec<i8> = src.iter().copied().repack_with(3, 6, options).unwrap().collect();
+    assert_eq!(r, expected);
+    assert_eq!(r, [-22, -31, 7]);
+
+    let mut iter = [-3i8, 4, 1].into_iter().repack_with::<i8>(3, 3, options).unwrap();
+    assert_eq!(iter.by_ref().collect::<Vec<_>>(), [-3]);
+    assert_eq!(iter.error(), Some(RepackError::ValueOverflow { index: 1, value: 4, bits_in: 3 }));
+}
@@ -0,0 +1,449 @@
+//! Битовая раскладка записей с именованными полями.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::{vec, vec::Vec};
+use core::str::FromStr;
+
+use crate::raw::{self, mask, sign_extend};
+use crate::{BitOrder, BitSlice, BitSliceMut, ByteOrder, LayoutError};
+
+/// Поле записи: имя, ширина, знаковость, порядок битов и положение.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{BitField, BitOrder};
+///     let field = BitField::new("offset", 11).signed(true).order(BitOrder::Lsb0).offset(21);
+///     assert_eq!(field.get_name(), "offset");
+///     assert_eq!(field.get_offset(), Some(21));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BitField {
+    name: String,
+    bits: usize,
+    signed: bool,
+    order: BitOrder,
+    offset: Option<usize>,
+}
+
+impl BitField {
+    /// Беззнаковое поле из bits бит с порядком битов Msb0, которое
+    /// начинается сразу после предыдущего поля раскладки.
+    pub fn new(name: impl Into<String>, bits: usize) -> Self {
+        BitField {
+            name: name.into(),
+            bits,
+            signed: false,
+            order: BitOrder::Msb0,
+            offset: None,
+        }
+    }
+
+    /// Знаковое поле: значение хранится в дополнительном коде.
+    pub fn signed(mut self, signed: bool) -> Self {
+        self.signed = signed;
+        self
+    }
+
+    /// Порядок битов значения в записи.
+    pub fn order(mut self, order: BitOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Номер первого бита поля в записи.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Имя поля (пустое для безымянных полей).
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Ширина поля в битах.
+    pub fn get_bits(&self) -> usize {
+        self.bits
+    }
+
+    /// Знаковое ли поле.
+    pub fn get_signed(&self) -> bool {
+        self.signed
+    }
+
+    /// Порядок битов значения в записи.
+    pub fn get_order(&self) -> BitOrder {
+        self.order
+    }
+
+    /// Номер первого бита поля в записи. None - сразу после предыдущего поля.
+    /// У полей [`BitLayout`] всегда задан.
+    pub fn get_offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    fn start(&self) -> usize {
+        self.offset.unwrap_or(0)
+    }
+
+    fn end(&self) -> usize {
+        self.start() + self.bits
+    }
+}
+
+/// Раскладка записи фиксированной длины на поля.
+///
+/// Запись - последовательность байтов, биты которых идут от старшего к младшему.
+/// Значения полей представлены как `i128`, поэтому беззнаковые поля
+/// занимают не больше 127 бит, знаковые - не больше 128. Биты записи вне полей
+/// при кодировании не меняются (в [`BitLayout::encode`] - нулевые).
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{BitField, BitLayout};
+///     let layout = BitLayout::new(vec![
+///         BitField::new("version", 3),
+///         BitField::new("flags", 5),
+///         BitField::new("length", 13),
+///         BitField::new("offset", 11).signed(true),
+///     ])
+///     .unwrap();
+///     assert_eq!(layout, "version:u3 flags:u5 length:u13 offset:i11".parse().unwrap());
+///
+///     let bytes = layout.encode(&[5, 0b_10001, 4000, -2]).unwrap();
+///     assert_eq!(bytes, [0b_101_10001, 0b_0111_1101, 0b_0000_0111, 0b_1111_1110]);
+///     assert_eq!(layout.decode(&bytes), Ok(vec![5, 0b_10001, 4000, -2]));
+///     assert_eq!(layout.decode_map(&bytes).unwrap()["length"], 4000);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BitLayout {
+    fields: Vec<BitField>,
+    len: usize,
+}
+
+impl BitLayout {
+    /// Наибольшая длина записи в битах (512 МиБ), чтобы смещение поля не
+    /// приводило к переполнению или выделению огромного буфера в [`BitLayout::encode`].
+    pub const MAX_LEN: usize = u32::MAX as usize;
+
+    /// Раскладка из полей в заданном порядке. Длина записи - конец последнего
+    /// по положению поля.
+    ///
+    /// # Errors
+    /// * [`LayoutError::InvalidWidth`] - ширина поля равна нулю или слишком велика.
+    /// * [`LayoutError::TooLong`] - поле заканчивается дальше [`BitLayout::MAX_LEN`] бит.
+    /// * [`LayoutError::Overlap`] - поля пересекаются.
+    /// * [`LayoutError::DuplicateName`] - непустое имя поля повторяется.
+    pub fn new(fields: Vec<BitField>) -> Result<Self, LayoutError> {
+        Self::build(fields, None)
+    }
+
+    /// То же, что и [`BitLayout::new`], но с заданной длиной записи в битах.
+    ///
+    /// # Errors
+    /// Те же, что и у [`BitLayout::new`], а также
+    /// * [`LayoutError::OutOfBounds`] - поле выходит за пределы len бит.
+    /// * [`LayoutError::TooLong`] - len больше [`BitLayout::MAX_LEN`].
+    pub fn with_len(fields: Vec<BitField>, len: usize) -> Result<Self, LayoutError> {
+        Self::build(fields, Some(len))
+    }
+
+    fn build(mut fields: Vec<BitField>, len: Option<usize>) -> Result<Self, LayoutError> {
+        if let Some(len) = len.filter(|&len| len > Self::MAX_LEN) {
+            return Err(LayoutError::TooLong { len });
+        }
+        let mut next = 0;
+        for (index, field) in fields.iter_mut().enumerate() {
+            let max = if field.signed { 128 } else { 127 };
+            if field.bits < 1 || field.bits > max {
+                return Err(LayoutError::InvalidWidth { index, bits: field.bits });
+            }
+            let offset = *field.offset.get_or_insert(next);
+            next = offset.saturating_add(field.bits);
+            if next > Self::MAX_LEN {
+                return Err(LayoutError::TooLong { len: next });
+            }
+            if let Some(len) = len {
+                if next > len {
+                    return Err(LayoutError::OutOfBounds { index, end: next, len });
+                }
+            }
+        }
+
+        for (index, field) in fields.iter().enumerate() {
+            if !field.name.is_empty() && fields[..index].iter().any(|f| f.name == field.name) {
+                return Err(LayoutError::DuplicateName { index });
+            }
+        }
+
+        // Пересечения ищутся среди соседних по положению полей.
+        let mut order: Vec<usize> = (0..fields.len()).collect();
+        order.sort_by_key(|&i| (fields[i].start(), i));
+        for pair in order.windows(2) {
+            if fields[pair[1]].start() < fields[pair[0]].end() {
+                return Err(LayoutError::Overlap { first: pair[0], second: pair[1] });
+            }
+        }
+
+        let end = fields.iter().map(BitField::end).max().unwrap_or(0);
+        Ok(BitLayout { fields, len: len.unwrap_or(end) })
+    }
+
+    /// Поля раскладки с заданными положениями.
+    pub fn fields(&self) -> &[BitField] {
+        &self.fields
+    }
+
+    /// Номер поля с именем name.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| !name.is_empty() && f.name == name)
+    }
+
+    /// Длина записи в битах.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Пуста ли запись.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Длина записи в байтах.
+    pub fn byte_len(&self) -> usize {
+        self.len.div_ceil(8)
+    }
+
+    /// Значения всех полей записи в порядке полей раскладки.
+    ///
+    /// # Errors
+    /// * [`LayoutError::SrcTooShort`] - в bytes меньше [`BitLayout::byte_len`] байтов.
+    pub fn decode(&self, bytes: &[u8]) -> Result<Vec<i128>, LayoutError> {
+        self.check_len(bytes.len())?;
+        Ok(self.fields.iter().map(|f| read_field(bytes, f)).collect())
+    }
+
+    /// Значения именованных полей записи по именам.
+    ///
+    /// # Errors
+    /// Те же, что и у [`BitLayout::decode`].
+    pub fn decode_map(&self, bytes: &[u8]) -> Result<BTreeMap<&str, i128>, LayoutError> {
+        self.check_len(bytes.len())?;
+        let named = self.fields.iter().filter(|f| !f.name.is_empty());
+        Ok(named.map(|f| (f.get_name(), read_field(bytes, f))).collect())
+    }
+
+    /// Запись из значений всех полей в порядке полей раскладки.
+    /// Биты вне полей нулевые.
+    ///
+    /// # Errors
+    /// * [`LayoutError::ValueCount`] - кол-во значений не совпадает с кол-вом полей.
+    /// * [`LayoutError::ValueOutOfRange`] - значение не помещается в поле.
+    pub fn encode(&self, values: &[i128]) -> Result<Vec<u8>, LayoutError> {
+        let mut bytes = vec![0; self.byte_len()];
+        self.encode_into(values, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// То же, что и [`BitLayout::encode`], но запись изменяется в bytes.
+    /// Биты вне полей не меняются. При ошибке bytes не меняется.
+    ///
+    /// # Errors
+    /// Те же, что и у [`BitLayout::encode`], а также
+    /// * [`LayoutError::SrcTooShort`] - в bytes меньше [`BitLayout::byte_len`] байтов.
+    pub fn encode_into(&self, values: &[i128], bytes: &mut [u8]) -> Result<(), LayoutError> {
+        if values.len() != self.fields.len() {
+            return Err(LayoutError::ValueCount { len: values.len(), required: self.fields.len() });
+        }
+        self.check_len(bytes.len())?;
+        for (index, (field, &value)) in self.fields.iter().zip(values).enumerate() {
+            if !fits(field, value) {
+                return Err(LayoutError::ValueOutOfRange { index, value });
+            }
+        }
+        for (field, &value) in self.fields.iter().zip(values) {
+            write_field(bytes, field, value);
+        }
+        Ok(())
+    }
+
+    /// Запись из значений полей по именам. Поля, которых нет в values,
+    /// нулевые.
+    ///
+    /// # Errors
+    /// * [`LayoutError::UnknownField`] - в раскладке нет поля с именем из values.
+    /// * [`LayoutError::ValueOutOfRange`] - значение не помещается в поле.
+    pub fn encode_map(&self, values: &BTreeMap<&str, i128>) -> Result<Vec<u8>, LayoutError> {
+        let mut all = vec![0; self.fields.len()];
+        for (&name, &value) in values {
+            all[self.index_of(name).ok_or(LayoutError::UnknownField)?] = value;
+        }
+        self.encode(&all)
+    }
+
+    fn check_len(&self, len: usize) -> Result<(), LayoutError> {
+        if len < self.byte_len() {
+            return Err(LayoutError::SrcTooShort { len, required: self.byte_len() });
+        }
+        Ok(())
+    }
+}
+
+/// Раскладка из текстового описания: поля через пробел или запятую в виде
+/// `[имя:](u|i)ширина[l][@смещение]`, где `u` - беззнаковое поле, `i` -
+/// знаковое, `l` - порядок битов Lsb0, а смещение - номер первого бита поля.
+/// Без смещения поле начинается сразу после предыдущего.
+///
+/// # Errors
+/// * [`LayoutError::Parse`] - описание поля некорректно.
+/// * Ошибки [`BitLayout::new`].
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{BitField, BitLayout, BitOrder};
+///     let layout: BitLayout = "u3 u5 u13 i11".parse().unwrap();
+///     assert_eq!(layout.len(), 32);
+///     let layout: BitLayout = "kind:u4@4, crc:i12l".parse().unwrap();
+///     assert_eq!(layout.fields()[1], BitField::new("crc", 12).signed(true).order(BitOrder::Lsb0).offset(8));
+/// ```
+impl FromStr for BitLayout {
+    type Err = LayoutError;
+
+    fn from_str(spec: &str) -> Result<Self, LayoutError> {
+        let tokens = spec.split(|c: char| c.is_whitespace() || c == ',').filter(|t| !t.is_empty());
+        let mut fields = Vec::new();
+        for (token, text) in tokens.enumerate() {
+            fields.push(parse_field(text).ok_or(LayoutError::Parse { token })?);
+        }
+        BitLayout::new(fields)
+    }
+}
+
+// Поле из описания `[имя:](u|i)ширина[l][@смещение]`.
+fn parse_field(text: &str) -> Option<BitField> {
+    let (name, rest) = match text.split_once(':') {
+        Some(("", _)) => return None,
+        Some((name, rest)) => (name, rest),
+        None => ("", text),
+    };
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    let (rest, offset) = match rest.split_once('@') {
+        Some((rest, offset)) => (rest, Some(parse_number(offset)?)),
+        None => (rest, None),
+    };
+    let (signed, rest) = match rest.strip_prefix('u') {
+        Some(rest) => (false, rest),
+        None => (true, rest.strip_prefix('i')?),
+    };
+    let (order, rest) = match rest.strip_suffix('l') {
+        Some(rest) => (BitOrder::Lsb0, rest),
+        None => (BitOrder::Msb0, rest),
+    };
+    let field = BitField::new(name, parse_number(rest)?).signed(signed).order(order);
+    Some(match offset {
+        Some(offset) => field.offset(offset),
+        None => field,
+    })
+}
+
+// Десятичное число без знака.
+fn parse_number(text: &str) -> Option<usize> {
+    if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+// Значение поля записи.
+fn read_field(bytes: &[u8], field: &BitField) -> i128 {
+    let seq = raw::read_at(bytes, 8, BitOrder::Msb0, ByteOrder::Big, field.start(), field.bits);
+    let v: u128 = raw::store(seq, field.bits, field.order);
+    if field.signed {
+        sign_extend(v, field.bits) as i128
+    } else {
+        v as i128
+    }
+}
+
+// Помещается ли значение в поле.
+fn fits(field: &BitField, value: i128) -> bool {
+    let raw = value as u128;
+    if field.signed {
+        sign_extend(raw, field.bits) == raw
+    } else {
+        value >= 0 && raw >> field.bits == 0
+    }
+}
+
+// Записывает значение в поле, не меняя остальные биты.
+fn write_field(bytes: &mut [u8], field: &BitField, value: i128) {
+    let v = [value as u128 & mask(field.bits)];
+    let src = BitSlice::with_order(&v, field.bits, field.order).expect("field width is validated");
+    let mut dst = BitSliceMut::new(bytes, 8).expect("8 bits fit in u8");
+    dst.slice_mut(field.start()..field.end()).copy_from(&src);
+}
+
+// Кодирование и декодирование полей разной ширины, знаковости и порядка битов.
+#[test]
+fn test1() {
+    let layout: BitLayout = "a:u1 b:i7l c:u127@9 e:u5l@136 d:i128".parse().unwrap();
+    assert_eq!(layout.len(), 269);
+    assert_eq!(layout.byte_len(), 34);
+    let values = [1, -64, i128::MAX, 0b_00011, i128::MIN];
+    let bytes = layout.encode(&values).unwrap();
+    assert_eq!(bytes[0], 0b_1000_0001);
+    assert_eq!(bytes[1], 0b_0111_1111);
+    assert_eq!(bytes[17], 0b_1100_0100);
+    assert_eq!(layout.decode(&bytes), Ok(values.to_vec()));
+
+    // Биты вне полей сохраняются.
+    let mut bytes = vec![0xFF; 2];
+    let layout: BitLayout = "x:u4@2 y:i3l".parse().unwrap();
+    layout.encode_into(&[0b_1010, -2], &mut bytes).unwrap();
+    assert_eq!(bytes[0], 0b_1110_1001);
+    assert_eq!(bytes[1], 0b_1111_1111);
+    let map = layout.decode_map(&bytes).unwrap();
+    assert_eq!(map.into_iter().collect::<Vec<_>>(), [("x", 0b_1010), ("y", -2)]);
+
+    let values = BTreeMap::from([("y", 1)]);
+    assert_eq!(layout.encode_map(&values), Ok(vec![0b_0000_0010, 0]));
+}
+
+// Ошибки раскладки, разбора и кодирования.
+#[test]
+fn test2() {
+    let r = BitLayout::new(vec![BitField::new("a", 0)]);
+    assert_eq!(r, Err(LayoutError::InvalidWidth { index: 0, bits: 0 }));
+    let r = BitLayout::new(vec![BitField::new("a", 128)]);
+    assert_eq!(r, Err(LayoutError::InvalidWidth { index: 0, bits: 128 }));
+    let r = BitLayout::with_len(vec![BitField::new("a", 3), BitField::new("b", 6)], 8);
+    assert_eq!(r, Err(LayoutError::OutOfBounds { index: 1, end: 9, len: 8 }));
+    let r: Result<BitLayout, _> = "a:u8 b:u4@4".parse();
+    assert_eq!(r, Err(LayoutError::Overlap { first: 0, second: 1 }));
+    let r: Result<BitLayout, _> = "a:u8 u2 a:u4".parse();
+    assert_eq!(r, Err(LayoutError::DuplicateName { index: 2 }));
+    let r: Result<BitLayout, _> = "u3@18446744073709551615".parse();
+    assert_eq!(r, Err(LayoutError::TooLong { len: usize::MAX }));
+    let r: Result<BitLayout, _> = "u3 u5@4294967291".parse();
+    assert_eq!(r, Err(LayoutError::TooLong { len: BitLayout::MAX_LEN + 1 }));
+    assert_eq!("u3 u5@4294967290".parse::<BitLayout>().map(|l| l.len()), Ok(BitLayout::MAX_LEN));
+    let r = BitLayout::with_len(vec![], usize::MAX);
+    assert_eq!(r, Err(LayoutError::TooLong { len: usize::MAX }));
+    for spec in ["u3 x5", "u3 :u5", "u3 u", "u3 u5@", "u3 a-b:u5", "u3 u+5"] {
+        assert_eq!(spec.parse::<BitLayout>(), Err(LayoutError::Parse { token: 1 }), "{}", spec);
+    }
+
+    let layout: BitLayout = "a:u3 b:i3".parse().unwrap();
+    assert_eq!(layout.encode(&[1]), Err(LayoutError::ValueCount { len: 1, required: 2 }));
+    assert_eq!(layout.encode(&[8, 0]), Err(LayoutError::ValueOutOfRange { index: 0, value: 8 }));
+    assert_eq!(layout.encode(&[-1, 0]), Err(LayoutError::ValueOutOfRange { index: 0, value: -1 }));
+    assert_eq!(layout.encode(&[0, -5]), Err(LayoutError::ValueOutOfRange { index: 1, value: -5 }));
+    assert_eq!(layout.encode_map(&BTreeMap::from([("c", 1)])), Err(LayoutError::UnknownField));
+    assert_eq!(layout.decode(&[]), Err(LayoutError::SrcTooShort { len: 0, required: 1 }));
+}
@@ -1,5 +1,24 @@
 //! A library for working with bit sequences
+//!
+//! # Features
+//! * `std` (по умолчанию) - реализация `std::error::Error` для ошибок, чтение
+//!   битов из `std::io::Read` ([`IoSource`]) и запись в `std::io::Write`
+//!   ([`IoSink`]), упаковка на лету ([`RepackReader`], [`RepackWriter`]);
+//!   включает `alloc`.
+//! * `alloc` - функции, возвращающие `Vec` ([`repack`], [`repack_with`],
+//!   [`repack_pattern`]), [`BitVec`], раскладки записей ([`BitLayout`]),
+//!   преобразования `num::BigUint` ([`biguint_to_words`], [`words_to_biguint`])
+//!   и срезов `bool` ([`bits_to_words`], [`words_to_bits`]).
+//! * `derive` - макрос `#[derive(BitPack)]` для структур и перечислений с
+//!   полями фиксированной битовой ширины (см. [`BitPack`]).
+//! * `serde` - компактный битовый формат данных для serde ([`to_bytes`],
+//!   [`from_bytes`], [`BitSerializer`], [`BitDeserializer`]); включает `alloc`.
+//!
+//! Без `alloc` крейт работает в `no_std` окружении: упаковка в готовый срез
+//! выполняется [`repack_into`] и [`repack_into_with`], потоковая -
+//! [`Repacker`] и [`RepackExt`].
 
+#![no_std]
 #![deny(non_upper_case_globals)]
 #![deny(non_camel_case_types)]
 #![deny(non_snake_case)]
@@ -8,9 +27,78 @@
 #![deny(unused_imports)]
 #![deny(missing_docs)]
 
-use std::ops::{BitAnd, Shr, Shl, BitOrAssign};
-use num::{Integer};
-use std::convert::TryFrom;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+#[cfg(all(test, feature = "alloc"))]
+use alloc::string::ToString;
+
+#[cfg(feature = "alloc")]
+mod big;
+#[cfg(feature = "alloc")]
+mod bitvec;
+#[cfg(feature = "alloc")]
+mod bools;
+#[cfg(feature = "serde")]
+mod codec;
+mod error;
+mod fixed;
+#[cfg(feature = "std")]
+mod io;
+mod iter;
+#[cfg(feature = "alloc")]
+mod layout;
+mod options;
+mod pack;
+#[cfg(feature = "alloc")]
+mod pattern;
+mod plan;
+mod raw;
+mod reader;
+mod repacker;
+mod slice;
+mod word;
+mod writer;
+
+#[cfg(feature = "alloc")]
+pub use big::{biguint_to_words, words_to_biguint};
+#[cfg(feature = "alloc")]
+pub use bitvec::BitVec;
+#[cfg(feature = "alloc")]
+pub use bools::{bits_to_words, bits_to_words_with, words_to_bits, words_to_bits_with};
+#[cfg(feature = "serde")]
+pub use codec::{from_bytes, from_bytes_with, to_bytes, to_bytes_with, BitDeserializer, BitSerializer, IntEncoding, SerdeOptions};
+#[cfg(feature = "serde")]
+pub use error::SerdeError;
+pub use error::{LayoutError, PackError, ReadError, RepackError, UnpackError, WriteError};
+#[cfg(feature = "alloc")]
+pub use fixed::repack_const;
+pub use fixed::{repack_const_into, repacked_len_const};
+#[cfg(feature = "std")]
+pub use io::{RepackReader, RepackWriter};
+pub use iter::{RepackExt, RepackIter};
+#[cfg(feature = "alloc")]
+pub use layout::{BitField, BitLayout};
+pub use options::{BitOrder, ByteOrder, Padding, RepackOptions};
+#[cfg(feature = "derive")]
+pub use bits_rs_derive::BitPack;
+pub use pack::{pack_word, unpack_word, word_fits, BitPack};
+#[cfg(feature = "alloc")]
+pub use pattern::{pack_pattern, pack_pattern_with, repack_pattern, repack_pattern_with};
+pub use plan::RepackPlan;
+#[cfg(feature = "std")]
+pub use reader::IoSource;
+pub use reader::{BitReader, ByteSource};
+pub use repacker::Repacker;
+pub use slice::{BitIter, BitSlice, BitSliceMut};
+pub use word::Word;
+#[cfg(feature = "std")]
+pub use writer::IoSink;
+pub use writer::{BitWriter, ByteSink};
 
 /// Принимает на вход битовую последовательность (src.len() * bits_in),
 /// упакованную в срез целых чисел (src), по bits_in бит в каждом эл-те.
@@ -37,13 +125,14 @@ use std::convert::TryFrom;
 /// * `bits_limit` - ограничение кол-ва всех входных значащих битов.
 ///
 /// # Errors
-/// * `Err("bits_in < 1 || bits_out < 1 || bits_limit < 1")`
-/// * `Err("bits_in > T1::size")`
-/// * `Err("bits_out > T2::size")`
-/// * `Err("bits_limit % bits_out != 0")`
-/// * `Err("can't convert usize to T1")`
-/// * `Err("can't convert usize to T2")`
-/// * `Err("can't convert T1 to T2")`
+/// * [`RepackError::ZeroWidth`] - bits_in, bits_out или bits_limit равен нулю.
+/// * [`RepackError::BitsInTooLarge`] - bits_in больше размера T1.
+/// * [`RepackError::BitsOutTooLarge`] - bits_out больше размера T2.
+/// * [`RepackError::UnalignedBitsLimit`] - bits_limit не делится на bits_out.
+/// * [`RepackError::WidthTooLarge`] - bits_in или bits_out больше 128.
+/// * [`RepackError::UnsupportedWord`] - T2 не представляет младший бит.
+///
+/// Последние две ошибки возможны только для пользовательских реализаций [`Word`].
 ///
 /// # Examples
 ///
@@ -60,76 +149,488 @@ use std::convert::TryFrom;
 ///     let r: Vec<u8> = bits_rs::repack(&src, 3, 4, 8).unwrap();
 ///     assert_eq!(dst, r.as_slice());
 /// ```
-pub fn repack<T1, T2>(src: &[T1], bits_in: usize, bits_out: usize, bits_limit: usize) -> Result<Vec<T2>, &'static str>
+///
+/// ```
+///     use bits_rs::RepackError;
+///     let src = [5u16, 5];
+///     let r = bits_rs::repack::<u16, u8>(&src, 3, 4, 6);
+///     assert_eq!(r, Err(RepackError::UnalignedBitsLimit { bits_limit: 6, bits_out: 4 }));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn repack<T1, T2>(src: &[T1], bits_in: usize, bits_out: usize, bits_limit: usize) -> Result<Vec<T2>, RepackError>
+where
+    T1: Word,
+    T2: Word,
+{
+    repack_with(src, bits_in, bits_out, bits_limit, RepackOptions::new())
+}
+
+/// То же, что и [`repack`], но с дополнительными параметрами упаковки.
+///
+/// Порядок битов задается отдельно для входного и выходного срезов, так что
+/// за один вызов можно, например, переложить LSB-first поток (DEFLATE, GIF LZW)
+/// в MSB-first эл-ты. Порядок байтов многобайтовых эл-тов
+/// ([`RepackOptions::src_byte_order`], [`RepackOptions::dst_byte_order`])
+/// сочетается с порядком битов: например, упакованные little-endian отсчеты
+/// разбираются с [`BitOrder::Lsb0`] на входе и выходе, а отсчеты, выровненные
+/// по байтам, - с [`ByteOrder::Little`].
+///
+/// Если bits_limit не делится на bits_out, результат определяется
+/// [`RepackOptions::padding`]: ошибка, дополнение последнего эл-та или
+/// отбрасывание неполного эл-та.
+///
+/// [`RepackOptions::src_offset`] позволяет взять биты
+/// `[src_offset, src_offset + bits_limit)` входной последовательности,
+/// а [`RepackOptions::dst_offset`] - записать их, начиная с бита dst_offset
+/// выходной последовательности. В этом случае эл-ты результата до dst_offset
+/// нулевые, а неполный последний эл-т определяется значением
+/// dst_offset + bits_limit.
+///
+/// # Arguments
+/// * `src` - срез с данными.
+/// * `bits_in` - кол-во значащих бит (справа) в каждом эл-те входного среза.
+/// * `bits_out` - кол-во значащих бит (справа) в каждом эл-те выходного среза.
+/// * `bits_limit` - ограничение кол-ва всех входных значащих битов.
+/// * `options` - параметры упаковки.
+///
+/// # Errors
+/// Те же, что и у [`repack`]. [`RepackError::UnalignedBitsLimit`] возвращается
+/// только при [`Padding::Reject`].
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{BitOrder, RepackOptions};
+///     let src = [0b_011u8, 0b_110]; // LSB-first: 110 011
+///     let options = RepackOptions::new().src_order(BitOrder::Lsb0);
+///     let r: Vec<u8> = bits_rs::repack_with(&src, 3, 2, 6, options).unwrap();
+///     assert_eq!(r, [0b_11, 0b_00, 0b_11]);
+/// ```
+///
+/// ```
+///     use bits_rs::{BitOrder, RepackOptions};
+///     // 20-битные отсчеты 0x12345 и 0xABCDE, упакованные little-endian.
+///     let src = [0x45u8, 0x23, 0xE1, 0xCD, 0xAB];
+///     let options = RepackOptions::new().src_order(BitOrder::Lsb0).dst_order(BitOrder::Lsb0);
+///     let r: Vec<u32> = bits_rs::repack_with(&src, 8, 20, 40, options).unwrap();
+///     assert_eq!(r, [0x1_2345, 0xA_BCDE]);
+/// ```
+///
+/// ```
+///     use bits_rs::{Padding, RepackOptions};
+///     let src = [0b_11111u8; 7]; // 35 бит
+///     let options = RepackOptions::new().padding(Padding::PadZeros);
+///     let r: Vec<u8> = bits_rs::repack_with(&src, 5, 8, 35, options).unwrap();
+///     assert_eq!(r, [0xFF, 0xFF, 0xFF, 0xFF, 0b_1110_0000]);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn repack_with<T1, T2>(
+    src: &[T1],
+    bits_in: usize,
+    bits_out: usize,
+    bits_limit: usize,
+    options: RepackOptions,
+) -> Result<Vec<T2>, RepackError>
+where
+    T1: Word,
+    T2: Word,
+{
+    validate::<T1, T2>(bits_in, bits_out, bits_limit, options)?;
+
+    let mut dst = vec![T2::from_raw(0); repacked_len_with(bits_out, bits_limit, options)];
+    repack_into_with(src, bits_in, &mut dst, bits_out, bits_limit, options)?;
+
+    Ok(dst)
+}
+
+/// То же, что и [`repack_with`], но дополнительно возвращает кол-во значащих
+/// бит в последнем эл-те результата (см. [`last_word_bits`]). При ненулевом
+/// [`RepackOptions::dst_offset`] оно определяется значением dst_offset + bits_limit.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{Padding, RepackOptions};
+///     let src = [1u8, 2, 3, 4, 5, 6, 7];
+///     let options = RepackOptions::new().padding(Padding::PadOnes);
+///     let (r, bits) = bits_rs::repack_padded::<u8, u8>(&src, 5, 8, 35, options).unwrap();
+///     assert_eq!(r.len(), 5);
+///     assert_eq!(bits, 3);
+///     assert_eq!(r[4] & 0b_0001_1111, 0b_0001_1111);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn repack_padded<T1, T2>(
+    src: &[T1],
+    bits_in: usize,
+    bits_out: usize,
+    bits_limit: usize,
+    options: RepackOptions,
+) -> Result<(Vec<T2>, usize), RepackError>
+where
+    T1: Word,
+    T2: Word,
+{
+    let dst = repack_with(src, bits_in, bits_out, bits_limit, options)?;
+    let end = window_end(options.get_dst_offset(), bits_limit)?;
+    Ok((dst, last_word_bits(bits_out, end, options.get_padding())))
+}
+
+/// То же, что и [`repack`], но результат записывается в переданный срез
+/// вместо выделения нового вектора.
+///
+/// Записывает [`repacked_len`]`(bits_out, bits_limit)` эл-тов
+/// ([`repacked_len_with`] для [`repack_into_with`]) в начало dst,
+/// остальные эл-ты dst не меняются. Если dst слишком мал, ничего не записывает
+/// и возвращает ошибку.
+///
+/// # Arguments
+/// * `src` - срез с данными.
+/// * `bits_in` - кол-во значащих бит (справа) в каждом эл-те входного среза.
+/// * `dst` - срез для результата.
+/// * `bits_out` - кол-во значащих бит (справа) в каждом эл-те выходного среза.
+/// * `bits_limit` - ограничение кол-ва всех входных значащих битов.
+///
+/// Возвращает кол-во записанных эл-тов.
+///
+/// # Errors
+/// Те же, что и у [`repack`], а также
+/// * [`RepackError::DstTooSmall`] - в dst меньше эл-тов, чем нужно.
+/// * [`RepackError::ValueTooWide`] - эл-т src шире bits_in
+///   (при [`RepackOptions::strict_values`]).
+/// * [`RepackError::SrcTooShort`] - в src меньше бит, чем нужно
+///   (при [`RepackOptions::strict_length`]).
+/// * [`RepackError::ValueOverflow`] - эл-т src не помещается в bits_in бит
+///   дополнительного кода (при [`RepackOptions::signed`]).
+/// * [`RepackError::LengthOverflow`] - src_offset + bits_limit или
+///   dst_offset + bits_limit не помещается в `usize`.
+///
+/// # Examples
+///
+/// ```
+///     let src = [5u16, 5]; // [0b_101, 0b_101]
+///     let mut dst = [0u8; 4];
+///     let n = bits_rs::repack_into(&src, 3, &mut dst, 2, 6).unwrap();
+///     assert_eq!(n, 3);
+///     assert_eq!(dst, [0b_10, 0b_11, 0b_01, 0]);
+/// ```
+pub fn repack_into<T1, T2>(
+    src: &[T1],
+    bits_in: usize,
+    dst: &mut [T2],
+    bits_out: usize,
+    bits_limit: usize,
+) -> Result<usize, RepackError>
+where
+    T1: Word,
+    T2: Word,
+{
+    repack_into_with(src, bits_in, dst, bits_out, bits_limit, RepackOptions::new())
+}
+
+/// То же, что и [`repack_into`], но с дополнительными параметрами упаковки
+/// (см. [`repack_with`]).
+///
+/// Биты dst до [`RepackOptions::dst_offset`] не меняются, так что результат
+/// можно дописывать к уже записанным данным. Неполный последний эл-т
+/// дополняется согласно [`RepackOptions::padding`].
+///
+/// Возвращает кол-во эл-тов dst, занятых результатом, включая эл-ты до dst_offset.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::RepackOptions;
+///     // 13-битное поле, начинающееся с 5-го бита заголовка.
+///     let header = [0b_1111_1101u8, 0b_0101_0100, 0b_0111_1111];
+///     let options = RepackOptions::new().src_offset(5).dst_offset(3);
+///     let mut dst = [0b_1110_0000_0000_0000u16];
+///     assert_eq!(bits_rs::repack_into_with(&header, 8, &mut dst, 16, 13, options), Ok(1));
+///     assert_eq!(dst, [0b_1111_0101_0101_0001]);
+/// ```
+pub fn repack_into_with<T1, T2>(
+    src: &[T1],
+    bits_in: usize,
+    dst: &mut [T2],
+    bits_out: usize,
+    bits_limit: usize,
+    options: RepackOptions,
+) -> Result<usize, RepackError>
+where
+    T1: Word,
+    T2: Word,
+{
+    validate::<T1, T2>(bits_in, bits_out, bits_limit, options)?;
+
+    // Биты переносятся целыми группами, а не по одному (см. raw::repack_words).
+    write_words(src, bits_in, dst, bits_out, bits_limit, options, |src, pos, dst| {
+        raw::repack_words(src, bits_in, pos, dst, bits_out, options)
+    })
+}
+
+// Запись результата repack_into_with с уже проверенными параметрами.
+// core заполняет целые выходные эл-ты битами src, начиная с бита pos.
+pub(crate) fn write_words<T1, T2, F>(
+    src: &[T1],
+    bits_in: usize,
+    dst: &mut [T2],
+    bits_out: usize,
+    bits_limit: usize,
+    options: RepackOptions,
+    core: F,
+) -> Result<usize, RepackError>
 where
-    T1: BitAnd<Output = T1> + Integer + Clone + Shr<Output = T1> + TryFrom<usize>,
-    T2: Integer + Clone + TryFrom<T1> + BitOrAssign + TryFrom<usize> + Shl<Output = T2>,
+    T1: Word,
+    T2: Word,
+    F: FnOnce(&[T1], usize, &mut [T2]),
 {
-    if bits_in < 1 || bits_out < 1 || bits_limit < 1 {
-        return Err("bits_in < 1 || bits_out < 1 || bits_limit < 1");
+    let padding = options.get_padding();
+    let (src_order, dst_order) = (options.get_src_order(), options.get_dst_order());
+    let (src_bytes, dst_bytes) = (options.get_src_byte_order(), options.get_dst_byte_order());
+    let dst_offset = options.get_dst_offset();
+    // Конец результата в выходной последовательности.
+    let end = window_end(dst_offset, bits_limit)?;
+
+    let len = repacked_len_with(bits_out, bits_limit, options);
+    if dst.len() < len {
+        return Err(RepackError::DstTooSmall { len: dst.len(), required: len });
+    }
+
+    check_src(src, bits_in, bits_limit, options)?;
+
+    // Позиция во входной последовательности и номер выходного эл-та.
+    let mut pos = options.get_src_offset();
+    let mut k = dst_offset / bits_out;
+    let full = end / bits_out;
+
+    // Первый эл-т, начало которого (до dst_offset) сохраняется.
+    let lead = dst_offset % bits_out;
+    if lead > 0 && k < full {
+        let n = bits_out - lead;
+        let old = raw::load_ordered(dst[k], bits_out, dst_order, dst_bytes) >> n << n;
+        let seq = old | raw::read_at(src, bits_in, src_order, src_bytes, pos, n);
+        dst[k] = raw::store_ordered(seq, bits_out, dst_order, dst_bytes);
+        pos += n;
+        k += 1;
+    }
+
+    if k < full {
+        core(src, pos, &mut dst[k..full]);
+        pos += (full - k) * bits_out;
     }
 
-    let src_bit_size = std::mem::size_of_val(&T1::zero()) * 8;
-    if bits_in > src_bit_size {
-        return Err("bits_in > T1::size");
+    // Неполный последний эл-т, если его нужно дополнить.
+    // Если он же и первый, его начало тоже сохраняется.
+    if len > full {
+        let rest = end - full * bits_out;
+        let lead = dst_offset.saturating_sub(full * bits_out);
+        let n = rest - lead;
+        let mut seq = raw::read_at(src, bits_in, src_order, src_bytes, pos, n);
+        if lead > 0 {
+            seq |= raw::load_ordered(dst[full], bits_out, dst_order, dst_bytes) >> (bits_out - lead) << n;
+        }
+        if let Some(seq) = raw::pad(seq, rest, bits_out, padding, dst_order, dst_bytes) {
+            dst[full] = raw::store_ordered(seq, bits_out, dst_order, dst_bytes);
+        }
     }
 
-    let dst_bit_size = std::mem::size_of_val(&T2::zero()) * 8;
-    if bits_out > dst_bit_size {
-        return Err("bits_out > T2::size");
+    // Расширение знака записанных эл-тов.
+    if options.get_signed() && T2::SIGNED && bits_out < T2::BITS {
+        for w in &mut dst[dst_offset / bits_out..len] {
+            *w = T2::from_raw(raw::sign_extend(w.to_raw(), bits_out));
+        }
     }
 
-    if bits_limit % bits_out != 0 {
-        return Err("bits_limit % bits_out != 0")
+    Ok(len)
+}
+
+/// Кол-во эл-тов, которое [`repack`] вернет для данных bits_out и bits_limit.
+/// Позволяет заранее подготовить срез для [`repack_into`].
+///
+/// При bits_out = 0 возвращает 0.
+///
+/// ```
+///     assert_eq!(bits_rs::repacked_len(2, 6), 3);
+/// ```
+pub const fn repacked_len(bits_out: usize, bits_limit: usize) -> usize {
+    match bits_limit.checked_div(bits_out) {
+        Some(len) => len,
+        None => 0,
     }
+}
+
+/// Кол-во эл-тов, которое [`repack_with`] вернет для данных bits_out,
+/// bits_limit и политики дополнения последнего эл-та.
+///
+/// Если dst_offset + bits_limit не помещается в `usize`, считается, что результат
+/// заканчивается на бите `usize::MAX` (сами функции упаковки в этом случае
+/// возвращают [`RepackError::LengthOverflow`]).
+///
+/// ```
+///     use bits_rs::{Padding, RepackOptions};
+///     let options = RepackOptions::new().padding(Padding::PadZeros);
+///     assert_eq!(bits_rs::repacked_len_with(8, 35, options), 5);
+///     let options = RepackOptions::new().padding(Padding::Truncate);
+///     assert_eq!(bits_rs::repacked_len_with(8, 35, options), 4);
+/// ```
+pub const fn repacked_len_with(bits_out: usize, bits_limit: usize, options: RepackOptions) -> usize {
+    let end = options.get_dst_offset().saturating_add(bits_limit);
+    let len = repacked_len(bits_out, end);
+    match options.get_padding() {
+        Padding::PadZeros | Padding::PadOnes | Padding::PadWith(_) if len * bits_out < end => len + 1,
+        _ => len,
+    }
+}
+
+/// Кол-во значащих бит в последнем эл-те результата [`repack_with`]:
+/// bits_limit % bits_out, если последний эл-т дополнен, иначе bits_out
+/// (0, если результат пуст).
+///
+/// ```
+///     use bits_rs::Padding;
+///     assert_eq!(bits_rs::last_word_bits(8, 35, Padding::PadZeros), 3);
+///     assert_eq!(bits_rs::last_word_bits(8, 35, Padding::Truncate), 8);
+///     assert_eq!(bits_rs::last_word_bits(8, 32, Padding::Reject), 8);
+/// ```
+pub const fn last_word_bits(bits_out: usize, bits_limit: usize, padding: Padding) -> usize {
+    if bits_out == 0 || bits_limit == 0 {
+        return 0;
+    }
+    let rest = bits_limit % bits_out;
+    match padding {
+        Padding::PadZeros | Padding::PadOnes | Padding::PadWith(_) if rest > 0 => rest,
+        _ if bits_limit < bits_out => 0,
+        _ => bits_out,
+    }
+}
+
+// Проверка параметров, общая для всех вариантов repack.
+pub(crate) fn validate<T1: Word, T2: Word>(
+    bits_in: usize,
+    bits_out: usize,
+    bits_limit: usize,
+    options: RepackOptions,
+) -> Result<(), RepackError> {
+    if bits_limit < 1 {
+        return Err(RepackError::ZeroWidth { bits_in, bits_out, bits_limit });
+    }
+
+    validate_widths::<T1, T2>(bits_in, bits_out, bits_limit)?;
+
+    // Результат заканчивается на dst_offset + bits_limit бите выходной последовательности.
+    let end = window_end(options.get_dst_offset(), bits_limit)?;
+    if options.get_padding() == Padding::Reject && !end.is_multiple_of(bits_out) {
+        return Err(RepackError::UnalignedBitsLimit { bits_limit: end, bits_out });
+    }
+
+    Ok(())
+}
+
+// Конец окна [offset, offset + bits_limit) последовательности.
+pub(crate) fn window_end(offset: usize, bits_limit: usize) -> Result<usize, RepackError> {
+    offset.checked_add(bits_limit).ok_or(RepackError::LengthOverflow)
+}
+
+// Проверки входного среза в строгих режимах.
+pub(crate) fn check_src<T1: Word>(src: &[T1], bits_in: usize, bits_limit: usize, options: RepackOptions) -> Result<(), RepackError> {
+    let start = options.get_src_offset();
+    let end = window_end(start, bits_limit)?;
+
+    if options.get_strict_length() && src.len() * bits_in < end {
+        return Err(RepackError::SrcTooShort { available: src.len() * bits_in, required: end });
+    }
+
+    let first = (start / bits_in).min(src.len());
+    let last = end.div_ceil(bits_in).min(src.len());
+    let window = src[first..last].iter().enumerate().map(|(i, &v)| (first + i, v));
+
+    if options.get_signed() && T1::SIGNED || options.get_strict_values() {
+        for (index, v) in window {
+            check_value(index, v, bits_in, options)?;
+        }
+    }
+
+    Ok(())
+}
 
-    let mut dst = vec![T2::zero(); bits_limit / bits_out];
+// Проверка входного эл-та с индексом index в строгом и знаковом режимах.
+pub(crate) fn check_value<T1: Word>(index: usize, v: T1, bits_in: usize, options: RepackOptions) -> Result<(), RepackError> {
+    if options.get_signed() && T1::SIGNED {
+        // В знаковом режиме старшие биты отрицательных чисел установлены,
+        // поэтому вместо strict_values проверяется диапазон значений.
+        if !raw::fits_signed(v, bits_in) {
+            return Err(RepackError::ValueOverflow { index, value: raw::value_of(v), bits_in });
+        }
+    } else if options.get_strict_values() && bits_in < T1::BITS && (v.to_raw() & raw::mask(T1::BITS)) >> bits_in != 0 {
+        return Err(RepackError::ValueTooWide { index, value: raw::value_of(v), bits_in });
+    }
+
+    Ok(())
+}
+
+// Проверка ширин эл-тов. bits_limit нужен только для текста ошибки.
+pub(crate) fn validate_widths<T1: Word, T2: Word>(bits_in: usize, bits_out: usize, bits_limit: usize) -> Result<(), RepackError> {
+    if bits_in < 1 || bits_out < 1 {
+        return Err(RepackError::ZeroWidth { bits_in, bits_out, bits_limit });
+    }
+
+    if bits_in > T1::BITS {
+        return Err(RepackError::BitsInTooLarge { bits_in, size: T1::BITS });
+    }
+
+    if bits_out > T2::BITS {
+        return Err(RepackError::BitsOutTooLarge { bits_out, size: T2::BITS });
+    }
+
+    // Биты перемещаются в 128-битном представлении эл-тов, что важно только
+    // для пользовательских реализаций Word.
+    if bits_in > 128 {
+        return Err(RepackError::WidthTooLarge { bits: bits_in });
+    }
+
+    if bits_out > 128 {
+        return Err(RepackError::WidthTooLarge { bits: bits_out });
+    }
+
+    if [0, 1].iter().any(|&value| T2::from_raw(value).to_raw() & 1 != value) {
+        return Err(RepackError::UnsupportedWord { size: T2::BITS });
+    }
+
+    Ok(())
+}
+
+// Побитовая реализация repack, с которой сверяется основная.
+#[cfg(all(test, feature = "alloc"))]
+fn repack_bitwise<T1: Word, T2: Word>(
+    src: &[T1],
+    bits_in: usize,
+    bits_out: usize,
+    bits_limit: usize,
+    options: RepackOptions,
+) -> Vec<T2> {
+    let mut dst = vec![0u128; bits_limit / bits_out];
     for i in 0..bits_limit {
-        // Номер входного байта
         let src_i = i / bits_in;
-        // Номер входного бита в байте
         let src_b = i % bits_in;
-        // Номер выходного байта
         let dst_i = i / bits_out;
-        // Номер выходного бита в байте
         let dst_b = i % bits_out;
 
-        // Сдвиг нужного бита в нулевую позицию.
-        let rsh = match T1::try_from(bits_in - src_b - 1) {
-            Ok(v) => v,
-            Err(_) => return Err("can't convert usize to T1"),
-        };
-
-        // Сдвиг бита влево в нужную позицию.
-        let lsh = match T2::try_from(bits_out - dst_b - 1) {
-            Ok(v) => v,
-            Err(_) => return Err("can't convert usize to T2"),
+        let rsh = match options.get_src_order() {
+            BitOrder::Msb0 => bits_in - src_b - 1,
+            BitOrder::Lsb0 => src_b,
         };
-
-        // Если кол-во бит в результате превышает кол-во входных бит, дополняем
-        // результат нулевыми битами.
-        let src_byte = if src_i < src.len() {
-            src[src_i].clone()
-        } else {
-            T1::zero()
-        };
-
-        // Текущий бит в текущем байте сдвигаем вправо и обрезаем до 1 бита.
-        // Получили значение исходного бита, затем сдвигаем его на позицию,
-        // в которой он должен находиться в выходном байте.
-        let src_bit = match T2::try_from((src_byte >> rsh) & T1::one()) {
-            Ok(v) => v,
-            Err(_) => return Err("can't convert T1 to T2"),
+        let lsh = match options.get_dst_order() {
+            BitOrder::Msb0 => bits_out - dst_b - 1,
+            BitOrder::Lsb0 => dst_b,
         };
 
-        // Копируем бит в выходной байт.
-        dst[dst_i] |= src_bit << lsh;
+        let src_byte = src.get(src_i).map_or(0, |v| v.to_raw());
+        dst[dst_i] |= ((src_byte >> rsh) & 1) << lsh;
     }
-
-    Ok(dst)
+    dst.into_iter().map(T2::from_raw).collect()
 }
 
 #[test]
+#[cfg(feature = "alloc")]
 fn test1() {
     let src = [0b_00101001_00010000_u16, 0b_00101001_00010000_u16];
     let dst = [0b_00101001_u8, 0b_00010000_u8, 0b_00101001u8, 0b_00010000_u8];
@@ -138,6 +639,7 @@ fn test1() {
 }
 
 #[test]
+#[cfg(feature = "alloc")]
 fn test2() {
     let src = [0xFF, 0xFF];
     let dst = [0u8, 0, 0, 0xFF, 0, 0, 0, 0xFF];
@@ -147,55 +649,60 @@ fn test2() {
 
 // Общее кол-во выходных бит нельзя поровну разделить на кол-во бит в выходном эл-те.
 #[test]
-#[should_panic(expected = "bits_limit % bits_out != 0")]
+#[cfg(feature = "alloc")]
 fn test3() {
     let src = [0xFF, 0xFF];
-    let dst = [0u8, 0, 0, 0xFF, 0, 0, 0, 0xFF];
-    let r: Vec<u8> = repack(&src, 32, 7, 64).unwrap();
-    assert_eq!(dst, r.as_slice());
+    let r = repack::<i32, u8>(&src, 32, 7, 64);
+    assert_eq!(r, Err(RepackError::UnalignedBitsLimit { bits_limit: 64, bits_out: 7 }));
 }
 
 // Кол-во значащих входных бит в одном эл-те превышает размер входного элемента.
 #[test]
-#[should_panic(expected = "bits_in > T1::size")]
+#[cfg(feature = "alloc")]
 fn test4() {
     let src = [0xFF, 0xFF];
-    let _: Vec<u8> = repack(&src, 256, 7, 64).unwrap();
+    let r = repack::<i32, u8>(&src, 256, 7, 64);
+    assert_eq!(r, Err(RepackError::BitsInTooLarge { bits_in: 256, size: 32 }));
 }
 
 // Кол-во значащих выходных бит в одном эл-те превышает размер выходного элемента.
 #[test]
-#[should_panic(expected = "bits_out > T2::size")]
+#[cfg(feature = "alloc")]
 fn test5() {
     let src = [0xFF, 0xFF];
-    let _: Vec<u8> = repack(&src, 32, 16, 64).unwrap();
+    let r = repack::<i32, u8>(&src, 32, 16, 64);
+    assert_eq!(r, Err(RepackError::BitsOutTooLarge { bits_out: 16, size: 8 }));
 }
 
 // Недопустимое значение bits_in.
 #[test]
-#[should_panic(expected = "bits_in < 1 || bits_out < 1 || bits_limit < 1")]
+#[cfg(feature = "alloc")]
 fn test6() {
     let src = [0xFF, 0xFF];
-    let _: Vec<u8> = repack(&src, 0, 16, 64).unwrap();
+    let r = repack::<i32, u8>(&src, 0, 16, 64);
+    assert_eq!(r, Err(RepackError::ZeroWidth { bits_in: 0, bits_out: 16, bits_limit: 64 }));
 }
 
 // Недопустимое значение bits_out.
 #[test]
-#[should_panic(expected = "bits_in < 1 || bits_out < 1 || bits_limit < 1")]
+#[cfg(feature = "alloc")]
 fn test7() {
     let src = [0xFF, 0xFF];
-    let _: Vec<u8> = repack(&src, 32, 0, 64).unwrap();
+    let r = repack::<i32, u8>(&src, 32, 0, 64);
+    assert_eq!(r, Err(RepackError::ZeroWidth { bits_in: 32, bits_out: 0, bits_limit: 64 }));
 }
 
 // Недопустимое значение bits_limit.
 #[test]
-#[should_panic(expected = "bits_in < 1 || bits_out < 1 || bits_limit < 1")]
+#[cfg(feature = "alloc")]
 fn test8() {
     let src = [0xFF, 0xFF];
-    let _: Vec<u8> = repack(&src, 32, 16, 0).unwrap();
+    let r = repack::<i32, u8>(&src, 32, 16, 0);
+    assert_eq!(r, Err(RepackError::ZeroWidth { bits_in: 32, bits_out: 16, bits_limit: 0 }));
 }
 
 #[test]
+#[cfg(feature = "alloc")]
 fn test9() {
     let src = [0b_00101001_u8, 0b_00010000_u8, 0b_00101001u8, 0b_00010000_u8];
     let dst = [0b_00101001_00010000_u16, 0b_00101001_00010000_u16];
@@ -204,9 +711,405 @@ fn test9() {
 }
 
 #[test]
+#[cfg(feature = "alloc")]
 fn test10() {
     let src = [5u16, 5]; // [0b_101, 0b_101]
     let dst = [11u8, 4]; // [0b_1011, 0b_0100]
     let r: Vec<u8> = repack(&src, 3, 4, 8).unwrap();
     assert_eq!(dst, r.as_slice());
 }
+
+// Текст ошибки содержит значения параметров.
+#[test]
+#[cfg(feature = "alloc")]
+fn test11() {
+    let e = RepackError::BitsInTooLarge { bits_in: 256, size: 32 };
+    assert_eq!(e.to_string(), "bits_in > T1::size (bits_in = 256, T1::size = 32)");
+}
+
+// LSB-first на входе, MSB-first на выходе.
+#[test]
+#[cfg(feature = "alloc")]
+fn test12() {
+    let src = [0b_1101_0011_u8, 0b_0000_1111_u8];
+    let options = RepackOptions::new().src_order(BitOrder::Lsb0);
+    let r: Vec<u16> = repack_with(&src, 8, 16, 16, options).unwrap();
+    assert_eq!(r, [0b_1100_1011_1111_0000_u16]);
+}
+
+// MSB-first на входе, LSB-first на выходе.
+#[test]
+#[cfg(feature = "alloc")]
+fn test13() {
+    let src = [5u16, 5]; // 101 101
+    let options = RepackOptions::new().dst_order(BitOrder::Lsb0);
+    let r: Vec<u8> = repack_with(&src, 3, 2, 6, options).unwrap();
+    assert_eq!(r, [0b_01, 0b_11, 0b_10]);
+}
+
+// LSB-first с обеих сторон сохраняет значения при совпадающей ширине.
+#[test]
+#[cfg(feature = "alloc")]
+fn test14() {
+    let src = [0x12u8, 0x34, 0x56];
+    let options = RepackOptions::new().src_order(BitOrder::Lsb0).dst_order(BitOrder::Lsb0);
+    let r: Vec<u16> = repack_with(&src, 8, 12, 24, options).unwrap();
+    assert_eq!(r, [0x412, 0x563]);
+}
+
+// Результат совпадает с побитовой реализацией для разных сочетаний ширин и порядков битов.
+#[test]
+#[cfg(feature = "alloc")]
+fn test15() {
+    let mut seed = 0x2545_F491_4F6C_DD1Du64;
+    let src: Vec<u128> = (0..64)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            ((seed as u128) << 64) | seed.rotate_left(29) as u128
+        })
+        .collect();
+    let orders = [BitOrder::Msb0, BitOrder::Lsb0];
+    for bits_in in [1, 3, 7, 8, 10, 12, 16, 31, 64, 100, 127, 128] {
+        for bits_out in [1, 2, 5, 8, 12, 16, 33, 64, 65, 128] {
+            for (src_order, dst_order) in orders.iter().flat_map(|&a| orders.iter().map(move |&b| (a, b))) {
+                let options = RepackOptions::new().src_order(src_order).dst_order(dst_order);
+                // Берем чуть больше бит, чем есть во входном срезе, чтобы проверить дополнение нулями.
+                let bits_limit = (src.len() * bits_in / bits_out + 3) * bits_out;
+                let r: Vec<u128> = repack_with(&src, bits_in, bits_out, bits_limit, options).unwrap();
+                let e: Vec<u128> = repack_bitwise(&src, bits_in, bits_out, bits_limit, options);
+                assert_eq!(r, e, "bits_in = {}, bits_out = {}, {:?}", bits_in, bits_out, options);
+            }
+        }
+    }
+}
+
+// Значения знаковых типов переносятся побитово, как и беззнаковых.
+#[test]
+#[cfg(feature = "alloc")]
+fn test16() {
+    let src = [-1i8, 0x12];
+    let r: Vec<u8> = repack(&src, 8, 4, 16).unwrap();
+    assert_eq!(r, [0xF, 0xF, 0x1, 0x2]);
+    let r: Vec<i16> = repack(&src, 4, 12, 12).unwrap();
+    assert_eq!(r, [0xF20]);
+}
+
+// Результат repack_into совпадает с repack, хвост dst не меняется.
+#[test]
+#[cfg(feature = "alloc")]
+fn test17() {
+    let src = [0x123u16, 0x456, 0x789];
+    let mut dst = [0xAAu8; 6];
+    let n = repack_into(&src, 12, &mut dst, 8, 32).unwrap();
+    assert_eq!(n, repacked_len(8, 32));
+    let r: Vec<u8> = repack(&src, 12, 8, 32).unwrap();
+    assert_eq!(&dst[..n], r.as_slice());
+    assert_eq!(&dst[n..], [0xAA, 0xAA]);
+}
+
+// Если dst мал, repack_into ничего в него не пишет.
+#[test]
+fn test18() {
+    let src = [0x123u16, 0x456];
+    let mut dst = [0xAAu8; 2];
+    let r = repack_into(&src, 12, &mut dst, 8, 24);
+    assert_eq!(r, Err(RepackError::DstTooSmall { len: 2, required: 3 }));
+    assert_eq!(dst, [0xAA, 0xAA]);
+}
+
+// Политики дополнения неполного последнего эл-та.
+#[test]
+#[cfg(feature = "alloc")]
+fn test19() {
+    let src = [0b_10110u8, 0b_01101];
+    let options = RepackOptions::new();
+
+    let r = repack_with::<u8, u8>(&src, 5, 4, 10, options);
+    assert_eq!(r, Err(RepackError::UnalignedBitsLimit { bits_limit: 10, bits_out: 4 }));
+
+    let r = repack_padded::<u8, u8>(&src, 5, 4, 10, options.padding(Padding::PadZeros));
+    assert_eq!(r, Ok((vec![0b_1011, 0b_0011, 0b_0100], 2)));
+
+    let r = repack_padded::<u8, u8>(&src, 5, 4, 10, options.padding(Padding::PadOnes));
+    assert_eq!(r, Ok((vec![0b_1011, 0b_0011, 0b_0111], 2)));
+
+    let r = repack_padded::<u8, u8>(&src, 5, 4, 10, options.padding(Padding::PadWith(0b_1010)));
+    assert_eq!(r, Ok((vec![0b_1011, 0b_0011, 0b_0110], 2)));
+
+    let r = repack_padded::<u8, u8>(&src, 5, 4, 10, options.padding(Padding::Truncate));
+    assert_eq!(r, Ok((vec![0b_1011, 0b_0011], 4)));
+
+    // Данных меньше, чем на один эл-т.
+    let r = repack_padded::<u8, u8>(&src, 5, 8, 5, options.padding(Padding::Truncate));
+    assert_eq!(r, Ok((vec![], 0)));
+}
+
+// При LSB-first на выходе дополняются старшие биты последнего эл-та.
+#[test]
+fn test20() {
+    let src = [0b_111u8];
+    let options = RepackOptions::new().dst_order(BitOrder::Lsb0).padding(Padding::PadWith(0b_0101_0000));
+    let mut dst = [0u8; 1];
+    assert_eq!(repack_into_with(&src, 3, &mut dst, 8, 3, options), Ok(1));
+    assert_eq!(dst, [0b_0101_0111]);
+}
+
+// Дополненный результат совпадает с Repacker::finish.
+#[test]
+#[cfg(feature = "alloc")]
+fn test21() {
+    let src: Vec<u16> = (0..37u16).map(|i| i.wrapping_mul(40503)).collect();
+    for padding in [Padding::PadZeros, Padding::PadOnes, Padding::PadWith(0x5A5A), Padding::Truncate] {
+        let options = RepackOptions::new().padding(padding);
+        let r: Vec<u16> = repack_with(&src, 13, 11, 37 * 13, options).unwrap();
+        let mut repacker = Repacker::<u16, u16>::new(13, 11).unwrap();
+        let mut e = Vec::new();
+        repacker.push(&src, &mut e).unwrap();
+        repacker.finish(padding, &mut e).unwrap();
+        assert_eq!(r, e, "{:?}", padding);
+    }
+}
+
+// Окно входной последовательности записывается с произвольной позиции выходной,
+// остальные биты выходного среза не меняются.
+#[test]
+#[cfg(feature = "alloc")]
+fn test22() {
+    let src: Vec<u16> = (0..40u16).map(|i| i.wrapping_mul(40503)).collect();
+    let bits: Vec<u8> = repack(&src, 16, 1, 640).unwrap();
+    for (src_offset, dst_offset, bits_limit) in [(0, 0, 40), (5, 3, 13), (17, 9, 2), (3, 6, 100), (600, 7, 60), (1, 0, 7)] {
+        for padding in [Padding::PadZeros, Padding::PadOnes, Padding::Truncate] {
+            let options = RepackOptions::new()
+                .src_offset(src_offset)
+                .dst_offset(dst_offset)
+                .padding(padding);
+            let mut dst = [0x5Au8; 20];
+            let n = repack_into_with(&src, 16, &mut dst, 8, bits_limit, options).unwrap();
+            assert_eq!(n, repacked_len_with(8, bits_limit, options));
+
+            // Та же упаковка по одному биту.
+            let mut expected: Vec<u8> = repack(&[0x5Au8; 20], 8, 1, 160).unwrap();
+            // При Truncate неполный последний эл-т не записывается.
+            for i in 0..bits_limit.min((n * 8).saturating_sub(dst_offset)) {
+                expected[dst_offset + i] = bits.get(src_offset + i).copied().unwrap_or(0);
+            }
+            let end = (dst_offset + bits_limit).min(n * 8);
+            match padding {
+                Padding::PadZeros => expected[end..n * 8].fill(0),
+                Padding::PadOnes => expected[end..n * 8].fill(1),
+                _ => {}
+            }
+            let expected: Vec<u8> = repack(&expected, 1, 8, 160).unwrap();
+            assert_eq!(dst, expected.as_slice(), "{} {} {} {:?}", src_offset, dst_offset, bits_limit, padding);
+        }
+    }
+}
+
+// repack_with со смещением в выходной последовательности дополняет начало нулями.
+#[test]
+#[cfg(feature = "alloc")]
+fn test23() {
+    let options = RepackOptions::new().dst_offset(4).padding(Padding::PadOnes);
+    let r: Vec<u8> = repack_with(&[0b_101u8], 3, 8, 3, options).unwrap();
+    assert_eq!(r, [0b_0000_1011]);
+    let r = repack_with::<u8, u8>(&[0b_101u8], 3, 8, 3, options.padding(Padding::Reject));
+    assert_eq!(r, Err(RepackError::UnalignedBitsLimit { bits_limit: 7, bits_out: 8 }));
+}
+
+// Строгая проверка входных эл-тов.
+#[test]
+fn test24() {
+    let options = RepackOptions::new().strict_values(true);
+    let mut dst = [0u8; 2];
+    let r = repack_into_with(&[1u16, 0xFF, 3], 3, &mut dst, 3, 6, options);
+    assert_eq!(r, Err(RepackError::ValueTooWide { index: 1, value: 0xFF, bits_in: 3 }));
+    assert_eq!(dst, [0, 0]);
+
+    // Эл-ты вне окна не проверяются.
+    let r = repack_into_with(&[1u16, 2, 0xFF], 3, &mut dst, 3, 6, options);
+    assert_eq!(r, Ok(2));
+    assert_eq!(dst, [1, 2]);
+
+    // Отрицательные значения знаковых типов не помещаются в bits_in < размера типа.
+    let r = repack_into_with(&[-1i8], 4, &mut dst, 4, 4, options);
+    assert_eq!(r, Err(RepackError::ValueTooWide { index: 0, value: -1, bits_in: 4 }));
+    let r = repack_into_with(&[-1i8], 8, &mut dst, 8, 8, options);
+    assert_eq!(r, Ok(1));
+}
+
+// Строгая проверка длины.
+#[test]
+fn test25() {
+    let options = RepackOptions::new().strict_length(true);
+    let mut dst = [0u8; 4];
+    let r = repack_into_with(&[1u16, 2], 3, &mut dst, 3, 9, options);
+    assert_eq!(r, Err(RepackError::SrcTooShort { available: 6, required: 9 }));
+    let r = repack_into_with(&[1u16, 2], 3, &mut dst, 3, 6, options.src_offset(3));
+    assert_eq!(r, Err(RepackError::SrcTooShort { available: 6, required: 9 }));
+    let r = repack_into_with(&[1u16, 2], 3, &mut dst, 3, 6, options);
+    assert_eq!(r, Ok(2));
+}
+
+// Знаковый режим: упаковка отрицательных чисел в 12-битные поля и распаковка обратно.
+#[test]
+fn test26() {
+    let options = RepackOptions::new().signed(true);
+    let src = [-1i16, 2047, -2048, 5];
+    let mut packed = [0u8; 6];
+    assert_eq!(repack_into_with(&src, 12, &mut packed, 8, 48, options), Ok(6));
+    assert_eq!(packed, [0xFF, 0xF7, 0xFF, 0x80, 0x00, 0x05]);
+
+    let mut unpacked = [0i16; 4];
+    assert_eq!(repack_into_with(&packed, 8, &mut unpacked, 12, 48, options), Ok(4));
+    assert_eq!(unpacked, src);
+
+    // Без знакового режима знак не расширяется.
+    assert_eq!(repack_into(&packed, 8, &mut unpacked, 12, 48), Ok(4));
+    assert_eq!(unpacked, [0xFFF, 0x7FF, 0x800, 5]);
+}
+
+// Знаковый режим: значения вне диапазона bits_in бит дополнительного кода.
+#[test]
+fn test27() {
+    let options = RepackOptions::new().signed(true);
+    let mut dst = [0u8; 4];
+    let r = repack_into_with(&[-4i8, 3, 4], 3, &mut dst, 3, 9, options);
+    assert_eq!(r, Err(RepackError::ValueOverflow { index: 2, value: 4, bits_in: 3 }));
+    let r = repack_into_with(&[-5i8], 3, &mut dst, 3, 3, options);
+    assert_eq!(r, Err(RepackError::ValueOverflow { index: 0, value: -5, bits_in: 3 }));
+
+    // Беззнаковые входные эл-ты не проверяются, знак расширяется только в знаковых выходных.
+    let mut signed = [0i8; 2];
+    assert_eq!(repack_into_with(&[0b_1000_0111u8], 8, &mut signed, 4, 8, options), Ok(2));
+    assert_eq!(signed, [-8, 7]);
+    assert_eq!(repack_into_with(&[0b_1000_0111u8], 8, &mut dst, 4, 8, options), Ok(2));
+    assert_eq!(dst[..2], [8, 7]);
+}
+
+// 128-битные эл-ты, в том числе знаковые, упаковываются без ограничений.
+#[test]
+#[cfg(feature = "alloc")]
+fn test28() {
+    let src = [u128::MAX, 1];
+    let r: Vec<u64> = repack(&src, 128, 64, 256).unwrap();
+    assert_eq!(r, [u64::MAX, u64::MAX, 0, 1]);
+    let r: Vec<u128> = repack(&r, 64, 128, 256).unwrap();
+    assert_eq!(r, src);
+
+    let options = RepackOptions::new().signed(true);
+    let src = [i128::MIN, -1, i128::MAX];
+    let r: Vec<i128> = repack_with(&src, 128, 100, 300, options).unwrap();
+    assert_eq!(r, [-(1 << 99), (1 << 72) - 1, -1 - (1 << 43)]);
+}
+
+// Порядок байтов Little совпадает с упаковкой эл-тов с переставленными байтами.
+#[test]
+#[cfg(feature = "alloc")]
+fn test29() {
+    let src: Vec<u64> = (0..30u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15)).collect();
+    for (bits_in, bits_out) in [(8, 8), (16, 24), (20, 12), (64, 40), (13, 64), (24, 7)] {
+        for order in [BitOrder::Msb0, BitOrder::Lsb0] {
+            let pattern = 0x5A5A_5A5A;
+            let options = RepackOptions::new().src_order(order).dst_order(BitOrder::Lsb0).src_offset(3).dst_offset(5);
+            let le = options
+                .src_byte_order(ByteOrder::Little)
+                .dst_byte_order(ByteOrder::Little)
+                .padding(Padding::PadWith(pattern));
+            let options = options.padding(Padding::PadWith(raw::little_to_seq(pattern, bits_out)));
+            let bits_limit = 30 * bits_in - 7;
+
+            let swapped: Vec<u64> = src.iter().map(|&v| raw::little_to_seq(v as u128, bits_in) as u64).collect();
+            let e: Vec<u64> = repack_with(&swapped, bits_in, bits_out, bits_limit, options).unwrap();
+            let e: Vec<u64> = e.iter().map(|&v| raw::seq_to_little(v as u128, bits_out) as u64).collect();
+            let r: Vec<u64> = repack_with(&src, bits_in, bits_out, bits_limit, le).unwrap();
+            assert_eq!(r, e, "{} {} {:?}", bits_in, bits_out, order);
+            assert_eq!(RepackPlan::new(bits_in, bits_out, bits_limit, le).unwrap().apply(&src), Ok(e));
+
+            let native = le.src_byte_order(ByteOrder::Native).dst_byte_order(ByteOrder::Native);
+            let r: Vec<u64> = repack_with(&src, bits_in, bits_out, bits_limit, native).unwrap();
+            assert_eq!(r == repack_with(&src, bits_in, bits_out, bits_limit, le).unwrap(), cfg!(target_endian = "little"));
+        }
+    }
+}
+
+// Порядок байтов: сохранение начала dst и знаковый режим.
+#[test]
+fn test30() {
+    // Первые 4 бита 0xABCD в порядке Little - 0xC.
+    let options = RepackOptions::new().dst_byte_order(ByteOrder::Little).dst_offset(4);
+    let mut dst = [0xABCDu16];
+    assert_eq!(repack_into_with(&[0x123u16], 12, &mut dst, 16, 12, options), Ok(1));
+    assert_eq!(dst, [0x23C1]);
+
+    let options = RepackOptions::new().src_byte_order(ByteOrder::Little).signed(true);
+    let mut dst = [0i8; 4];
+    assert_eq!(repack_into_with(&[-2i16, 0x1234], 16, &mut dst, 8, 32, options), Ok(4));
+    assert_eq!(dst, [-2, -1, 0x34, 0x12]);
+}
+
+// Пользовательские реализации Word: эл-ты шире 128 бит и типы,
+// не представляющие отдельные биты.
+#[test]
+fn test31() {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Wide(u128);
+
+    impl Word for Wide {
+        const BITS: usize = 256;
+        const SIGNED: bool = false;
+
+        fn to_raw(self) -> u128 {
+            self.0
+        }
+
+        fn from_raw(raw: u128) -> Self {
+            Wide(raw)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Even(u8);
+
+    impl Word for Even {
+        const BITS: usize = 8;
+        const SIGNED: bool = false;
+
+        fn to_raw(self) -> u128 {
+            self.0 as u128
+        }
+
+        fn from_raw(raw: u128) -> Self {
+            Even(raw as u8 & !1)
+        }
+    }
+
+    let mut dst = [Wide(0); 2];
+    assert_eq!(repack_into(&[Wide(5), Wide(3)], 128, &mut dst, 128, 256), Ok(2));
+    assert_eq!(dst, [Wide(5), Wide(3)]);
+    assert_eq!(repack_into(&[Wide(5)], 200, &mut dst, 8, 200), Err(RepackError::WidthTooLarge { bits: 200 }));
+    assert_eq!(repack_into(&[5u8], 8, &mut dst, 130, 130), Err(RepackError::WidthTooLarge { bits: 130 }));
+
+    let mut dst = [Even(0); 2];
+    assert_eq!(repack_into(&[5u8], 8, &mut dst, 4, 8), Err(RepackError::UnsupportedWord { size: 8 }));
+}
+
+// Кол-во значащих бит последнего эл-та при ненулевом dst_offset,
+// переполнение конца окна.
+#[test]
+#[cfg(feature = "alloc")]
+fn test32() {
+    let options = RepackOptions::new().dst_offset(3).padding(Padding::PadZeros);
+    let (r, bits) = repack_padded::<u8, u8>(&[0xFF], 8, 8, 8, options).unwrap();
+    assert_eq!((r, bits), (vec![0x1F, 0xE0], 3));
+    let options = options.dst_offset(5).padding(Padding::Truncate);
+    assert_eq!(repack_padded::<u8, u8>(&[0xFF], 8, 8, 3, options), Ok((vec![0x07], 8)));
+
+    let options = RepackOptions::new().dst_offset(usize::MAX - 2).padding(Padding::Truncate);
+    assert_eq!(repack_padded::<u8, u8>(&[0xFF], 8, 8, 8, options), Err(RepackError::LengthOverflow));
+    let mut dst = [0u8; 2];
+    assert_eq!(repack_into_with(&[0xFFu8], 8, &mut dst, 8, 8, options), Err(RepackError::LengthOverflow));
+    let options = RepackOptions::new().src_offset(usize::MAX).strict_length(true);
+    assert_eq!(repack_into_with(&[0xFFu8], 8, &mut dst, 8, 8, options), Err(RepackError::LengthOverflow));
+}
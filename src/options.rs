@@ -0,0 +1,276 @@
+//! Параметры упаковки битовых последовательностей.
+
+/// Порядок битов внутри эл-та.
+///
+/// Определяет, с какого бита эл-та начинается его часть битовой
+/// последовательности.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BitOrder {
+    /// Первым идет старший значащий бит эл-та (bits - 1), последним - нулевой.
+    #[default]
+    Msb0,
+    /// Первым идет нулевой бит эл-та, последним - старший значащий (bits - 1).
+    Lsb0,
+}
+
+/// Порядок байтов многобайтового эл-та.
+///
+/// Определяет, в каком порядке байты значащих бит эл-та идут в битовой
+/// последовательности. При Little первыми идут младшие 8 бит, последним -
+/// старший байт, неполный, если bits не делится на 8. Порядок битов
+/// ([`BitOrder`]) применяется к эл-ту уже после перестановки байтов, так что
+/// Msb0 с Little дает последовательность байтов от младшего к старшему,
+/// в каждом из которых биты идут от старшего к младшему.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{ByteOrder, RepackOptions};
+///     // 24-битные знаковые отсчеты PCM в порядке little-endian.
+///     let pcm = [0x01u8, 0x02, 0x80, 0xFF, 0xFF, 0x7F];
+///     let options = RepackOptions::new().dst_byte_order(ByteOrder::Little).signed(true);
+///     let mut r = [0i32; 2];
+///     bits_rs::repack_into_with(&pcm, 8, &mut r, 24, 48, options).unwrap();
+///     assert_eq!(r, [-0x7F_FDFF, 0x7F_FFFF]);
+///     let mut r = [0u16];
+///     bits_rs::repack_into_with(&[0x1234u16], 16, &mut r, 16, 16, options.src_byte_order(ByteOrder::Little)).unwrap();
+///     assert_eq!(r, [0x1234]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ByteOrder {
+    /// Первым идет старший байт (big-endian).
+    #[default]
+    Big,
+    /// Первым идет младший байт (little-endian).
+    Little,
+    /// Порядок байтов целевой платформы.
+    Native,
+}
+
+/// Что делать с неполным последним выходным эл-том, когда кол-во бит
+/// не делится на bits_out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Padding {
+    /// Вернуть ошибку [`RepackError::UnalignedBitsLimit`](crate::RepackError::UnalignedBitsLimit).
+    #[default]
+    Reject,
+    /// Дополнить последний эл-т нулевыми битами.
+    PadZeros,
+    /// Дополнить последний эл-т единичными битами.
+    PadOnes,
+    /// Взять недостающие биты последнего эл-та из тех же позиций шаблона.
+    PadWith(u128),
+    /// Отбросить неполный последний эл-т.
+    Truncate,
+}
+
+/// Параметры [`repack_with`](crate::repack_with).
+///
+/// По умолчанию оба среза читаются и пишутся в порядке [`BitOrder::Msb0`],
+/// а неполный последний эл-т считается ошибкой ([`Padding::Reject`]),
+/// что соответствует поведению [`repack`](crate::repack).
+///
+/// ```
+///     use bits_rs::{BitOrder, RepackOptions};
+///     let options = RepackOptions::new().src_order(BitOrder::Lsb0);
+///     assert_eq!(options.get_src_order(), BitOrder::Lsb0);
+///     assert_eq!(options.get_dst_order(), BitOrder::Msb0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct RepackOptions {
+    src_order: BitOrder,
+    dst_order: BitOrder,
+    padding: Padding,
+    src_offset: usize,
+    dst_offset: usize,
+    strict_values: bool,
+    strict_length: bool,
+    signed: bool,
+    src_byte_order: ByteOrder,
+    dst_byte_order: ByteOrder,
+    src_stream_byte_order: ByteOrder,
+    dst_stream_byte_order: ByteOrder,
+}
+
+impl RepackOptions {
+    /// Параметры по умолчанию.
+    pub const fn new() -> Self {
+        RepackOptions {
+            src_order: BitOrder::Msb0,
+            dst_order: BitOrder::Msb0,
+            padding: Padding::Reject,
+            src_offset: 0,
+            dst_offset: 0,
+            strict_values: false,
+            strict_length: false,
+            signed: false,
+            src_byte_order: ByteOrder::Big,
+            dst_byte_order: ByteOrder::Big,
+            src_stream_byte_order: ByteOrder::Big,
+            dst_stream_byte_order: ByteOrder::Big,
+        }
+    }
+
+    /// Порядок битов в эл-тах входного среза.
+    pub const fn src_order(mut self, order: BitOrder) -> Self {
+        self.src_order = order;
+        self
+    }
+
+    /// Порядок битов в эл-тах выходного среза.
+    pub const fn dst_order(mut self, order: BitOrder) -> Self {
+        self.dst_order = order;
+        self
+    }
+
+    /// Что делать с неполным последним выходным эл-том.
+    pub const fn padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Номер бита входной последовательности, с которого начинается упаковка.
+    /// Используется только функциями `repack_*`.
+    pub const fn src_offset(mut self, offset: usize) -> Self {
+        self.src_offset = offset;
+        self
+    }
+
+    /// Номер бита выходной последовательности, с которого записывается результат.
+    /// Биты выходного среза до него не меняются. Используется только функциями `repack_*`.
+    pub const fn dst_offset(mut self, offset: usize) -> Self {
+        self.dst_offset = offset;
+        self
+    }
+
+    /// Строгая проверка входных эл-тов: вместо того чтобы игнорировать биты
+    /// выше bits_in, вернуть ошибку [`RepackError::ValueTooWide`](crate::RepackError::ValueTooWide).
+    /// Используется функциями `repack_*`, [`RepackPlan`](crate::RepackPlan),
+    /// [`Repacker`](crate::Repacker) (и [`RepackWriter`](crate::RepackWriter),
+    /// [`RepackReader`](crate::RepackReader)), [`RepackExt::repack_with`](crate::RepackExt::repack_with),
+    /// [`repack_pattern_with`](crate::repack_pattern_with) и
+    /// [`pack_pattern_with`](crate::pack_pattern_with) (для ширины каждого поля).
+    pub const fn strict_values(mut self, strict: bool) -> Self {
+        self.strict_values = strict;
+        self
+    }
+
+    /// Строгая проверка длины: вместо того чтобы дополнять недостающие входные
+    /// биты нулями, вернуть ошибку [`RepackError::SrcTooShort`](crate::RepackError::SrcTooShort).
+    /// Используется функциями `repack_*`, [`RepackPlan`](crate::RepackPlan) и
+    /// [`repack_pattern_with`](crate::repack_pattern_with); у потоковой упаковки
+    /// длина входа заранее неизвестна.
+    pub const fn strict_length(mut self, strict: bool) -> Self {
+        self.strict_length = strict;
+        self
+    }
+
+    /// Знаковый режим: эл-ты рассматриваются как числа в дополнительном коде.
+    ///
+    /// Если T1 знаковый, каждый входной эл-т должен помещаться в bits_in бит
+    /// дополнительного кода, иначе возвращается
+    /// [`RepackError::ValueOverflow`](crate::RepackError::ValueOverflow).
+    /// Если T2 знаковый, знак каждого bits_out-битного выходного эл-та
+    /// расширяется на весь T2. Используется теми же функциями и типами, что и
+    /// [`RepackOptions::strict_values`].
+    pub const fn signed(mut self, signed: bool) -> Self {
+        self.signed = signed;
+        self
+    }
+
+    /// Порядок байтов значащих бит эл-тов входного среза в последовательности
+    /// (см. [`ByteOrder`]). Используется всеми вариантами упаковки, включая
+    /// [`Repacker`](crate::Repacker), [`RepackExt`](crate::RepackExt) и обертки
+    /// потоков. Порядок байтов эл-тов в самом потоке задается отдельно
+    /// ([`RepackOptions::src_stream_byte_order`]).
+    pub const fn src_byte_order(mut self, order: ByteOrder) -> Self {
+        self.src_byte_order = order;
+        self
+    }
+
+    /// Порядок байтов значащих бит эл-тов выходного среза в последовательности
+    /// (см. [`ByteOrder`]). Используется всеми вариантами упаковки, включая
+    /// [`Repacker`](crate::Repacker), [`RepackExt`](crate::RepackExt) и обертки
+    /// потоков. Порядок байтов эл-тов в самом потоке задается отдельно
+    /// ([`RepackOptions::dst_stream_byte_order`]).
+    pub const fn dst_byte_order(mut self, order: ByteOrder) -> Self {
+        self.dst_byte_order = order;
+        self
+    }
+
+    /// Порядок байтов, в котором [`RepackReader`](crate::RepackReader) и
+    /// [`RepackWriter`](crate::RepackWriter) собирают входные эл-ты T1 из потока
+    /// байтов. Функциями `repack_*` не используется.
+    pub const fn src_stream_byte_order(mut self, order: ByteOrder) -> Self {
+        self.src_stream_byte_order = order;
+        self
+    }
+
+    /// Порядок байтов, в котором [`RepackReader`](crate::RepackReader) и
+    /// [`RepackWriter`](crate::RepackWriter) выдают в поток байтов выходные эл-ты T2.
+    /// Функциями `repack_*` не используется.
+    pub const fn dst_stream_byte_order(mut self, order: ByteOrder) -> Self {
+        self.dst_stream_byte_order = order;
+        self
+    }
+
+    /// Порядок битов в эл-тах входного среза.
+    pub const fn get_src_order(&self) -> BitOrder {
+        self.src_order
+    }
+
+    /// Порядок битов в эл-тах выходного среза.
+    pub const fn get_dst_order(&self) -> BitOrder {
+        self.dst_order
+    }
+
+    /// Что делать с неполным последним выходным эл-том.
+    pub const fn get_padding(&self) -> Padding {
+        self.padding
+    }
+
+    /// Номер бита входной последовательности, с которого начинается упаковка.
+    pub const fn get_src_offset(&self) -> usize {
+        self.src_offset
+    }
+
+    /// Номер бита выходной последовательности, с которого записывается результат.
+    pub const fn get_dst_offset(&self) -> usize {
+        self.dst_offset
+    }
+
+    /// Включена ли строгая проверка входных эл-тов.
+    pub const fn get_strict_values(&self) -> bool {
+        self.strict_values
+    }
+
+    /// Включена ли строгая проверка длины.
+    pub const fn get_strict_length(&self) -> bool {
+        self.strict_length
+    }
+
+    /// Включен ли знаковый режим.
+    pub const fn get_signed(&self) -> bool {
+        self.signed
+    }
+
+    /// Порядок байтов эл-тов входного среза.
+    pub const fn get_src_byte_order(&self) -> ByteOrder {
+        self.src_byte_order
+    }
+
+    /// Порядок байтов эл-тов выходного среза.
+    pub const fn get_dst_byte_order(&self) -> ByteOrder {
+        self.dst_byte_order
+    }
+
+    /// Порядок байтов входных эл-тов в потоке.
+    pub const fn get_src_stream_byte_order(&self) -> ByteOrder {
+        self.src_stream_byte_order
+    }
+
+    /// Порядок байтов выходных эл-тов в потоке.
+    pub const fn get_dst_stream_byte_order(&self) -> ByteOrder {
+        self.dst_stream_byte_order
+    }
+}
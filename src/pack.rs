@@ -0,0 +1,221 @@
+//! Типы фиксированной битовой ширины, упаковываемые в байты.
+//!
+//! Упакованное значение занимает биты `[lo, lo + BITS)` целого числа
+//! размером `bytes.len() * 8` бит, младший бит которого - нулевой.
+//! Число хранится в срезе байтов в заданном порядке байтов, так что
+//! нулевой бит находится в последнем байте при [`ByteOrder::Big`] и
+//! в первом - при [`ByteOrder::Little`].
+
+use crate::raw::{big_endian, mask, shl, shr, sign_extend};
+use crate::{ByteOrder, PackError, UnpackError, Word};
+
+/// Значение, занимающее ровно [`BitPack::BITS`] бит.
+///
+/// Реализован для `bool` (1 бит) и всех примитивных целых типов.
+/// Для структур и перечислений выводится макросом `#[derive(BitPack)]`
+/// (feature `derive`), который кроме реализации трейта добавляет типу методы
+/// `pack(&self) -> [u8; N]`, `try_pack(&self) -> Result<[u8; N], PackError>` и
+/// `unpack(&[u8]) -> Result<Self, UnpackError>`.
+///
+/// [`BitPack::write_bits`] не проверяет значения: поле с атрибутом `#[bits(n)]`,
+/// значение которого не помещается в n бит, усекается до младших n бит
+/// (например, `0xFF` в 5-битном поле записывается как `0x1F`). Такие значения
+/// находит [`BitPack::check_fits`], который вызывает `try_pack`.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{BitPack, ByteOrder};
+///     let mut bytes = [0u8; 2];
+///     0x2Du8.write_bits(&mut bytes, ByteOrder::Big, 4);
+///     true.write_bits(&mut bytes, ByteOrder::Big, 0);
+///     assert_eq!(bytes, [0x02, 0xD1]);
+///     assert_eq!(u8::read_bits(&bytes, ByteOrder::Big, 4), Ok(0x2D));
+/// ```
+pub trait BitPack: Sized {
+    /// Кол-во бит упакованного значения.
+    const BITS: usize;
+
+    /// Записывает значение в биты `[lo, lo + BITS)`, не изменяя остальные.
+    ///
+    /// # Panics
+    /// Если `lo + BITS` больше `bytes.len() * 8`.
+    fn write_bits(&self, bytes: &mut [u8], order: ByteOrder, lo: usize);
+
+    /// Проверяет, что значения всех полей помещаются в свои ширины и
+    /// [`BitPack::write_bits`] запишет их без усечения. Для `bool`, целых
+    /// типов и перечислений всегда успешна.
+    ///
+    /// # Errors
+    /// * [`PackError::ValueOutOfRange`] - значение поля не помещается в его ширину.
+    fn check_fits(&self) -> Result<(), PackError> {
+        Ok(())
+    }
+
+    /// Читает значение из бит `[lo, lo + BITS)`.
+    ///
+    /// # Errors
+    /// * [`UnpackError::InvalidDiscriminant`] - биты не соответствуют ни одному
+    ///   варианту перечисления.
+    ///
+    /// # Panics
+    /// Если `lo + BITS` больше `bytes.len() * 8`.
+    fn read_bits(bytes: &[u8], order: ByteOrder, lo: usize) -> Result<Self, UnpackError>;
+}
+
+/// Записывает младшие bits бит эл-та v в биты `[lo, lo + bits)`,
+/// не изменяя остальные.
+///
+/// # Panics
+/// Если `lo + bits` больше `bytes.len() * 8`.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::ByteOrder;
+///     let mut bytes = [0xFFu8; 2];
+///     bits_rs::pack_word(&mut bytes, ByteOrder::Little, 6, 4, -6i8);
+///     assert_eq!(bytes, [0xBF, 0xFE]);
+/// ```
+pub fn pack_word<T: Word>(bytes: &mut [u8], order: ByteOrder, lo: usize, bits: usize, v: T) {
+    let (len, big) = (bytes.len(), big_endian(order));
+    let mut v = v.to_raw() & mask(bits);
+    let (mut pos, end) = (lo, lo + bits);
+    while pos < end {
+        let (shift, take) = (pos % 8, (8 - pos % 8).min(end - pos));
+        let i = if big { len - 1 - pos / 8 } else { pos / 8 };
+        let m = (mask(take) as u8) << shift;
+        bytes[i] = (bytes[i] & !m) | (((v as u8) << shift) & m);
+        v = shr(v, take);
+        pos += take;
+    }
+}
+
+/// Помещается ли эл-т v в bits бит: для беззнаковых типов - без старших
+/// бит, для знаковых - в диапазон знакового bits-битного числа.
+///
+/// # Examples
+///
+/// ```
+///     assert!(bits_rs::word_fits(31u8, 5));
+///     assert!(!bits_rs::word_fits(32u8, 5));
+///     assert!(bits_rs::word_fits(-8i8, 4));
+///     assert!(!bits_rs::word_fits(9i8, 4));
+/// ```
+pub fn word_fits<T: Word>(v: T, bits: usize) -> bool {
+    let raw = v.to_raw();
+    if T::SIGNED && bits > 0 {
+        sign_extend(raw & mask(bits), bits) == raw
+    } else {
+        raw & mask(bits) == raw
+    }
+}
+
+/// Эл-т из бит `[lo, lo + bits)`. Для знаковых типов старший из bits бит
+/// считается знаковым.
+///
+/// # Panics
+/// Если `lo + bits` больше `bytes.len() * 8`.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::ByteOrder;
+///     let bytes = [0xBF, 0xFE];
+///     assert_eq!(bits_rs::unpack_word::<i8>(&bytes, ByteOrder::Little, 6, 4), -6);
+///     assert_eq!(bits_rs::unpack_word::<u8>(&bytes, ByteOrder::Little, 6, 4), 10);
+/// ```
+pub fn unpack_word<T: Word>(bytes: &[u8], order: ByteOrder, lo: usize, bits: usize) -> T {
+    let (len, big) = (bytes.len(), big_endian(order));
+    let mut v = 0u128;
+    let (mut pos, end) = (lo, lo + bits);
+    while pos < end {
+        let (shift, take) = (pos % 8, (8 - pos % 8).min(end - pos));
+        let i = if big { len - 1 - pos / 8 } else { pos / 8 };
+        v |= shl(((bytes[i] >> shift) as u128) & mask(take), pos - lo);
+        pos += take;
+    }
+    if T::SIGNED && bits > 0 {
+        v = sign_extend(v, bits);
+    }
+    T::from_raw(v)
+}
+
+impl BitPack for bool {
+    const BITS: usize = 1;
+
+    #[inline]
+    fn write_bits(&self, bytes: &mut [u8], order: ByteOrder, lo: usize) {
+        pack_word(bytes, order, lo, 1, *self as u8);
+    }
+
+    #[inline]
+    fn read_bits(bytes: &[u8], order: ByteOrder, lo: usize) -> Result<Self, UnpackError> {
+        Ok(unpack_word::<u8>(bytes, order, lo, 1) != 0)
+    }
+}
+
+macro_rules! impl_bit_pack {
+    ($($t:ty),*) => {$(
+        impl BitPack for $t {
+            const BITS: usize = <$t as Word>::BITS;
+
+            #[inline]
+            fn write_bits(&self, bytes: &mut [u8], order: ByteOrder, lo: usize) {
+                pack_word(bytes, order, lo, <Self as BitPack>::BITS, *self);
+            }
+
+            #[inline]
+            fn read_bits(bytes: &[u8], order: ByteOrder, lo: usize) -> Result<Self, UnpackError> {
+                Ok(unpack_word(bytes, order, lo, <Self as BitPack>::BITS))
+            }
+        }
+    )*};
+}
+
+impl_bit_pack!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+// Запись и чтение полей, пересекающих границы байтов, в обоих порядках байтов.
+#[test]
+fn test1() {
+    for order in [ByteOrder::Big, ByteOrder::Little] {
+        for (lo, bits) in [(0, 1), (3, 5), (5, 13), (7, 64), (0, 128), (9, 119)] {
+            let mut bytes = [0xA5u8; 17];
+            let v = 0x0123_4567_89AB_CDEF_FEDC_BA98_7654_3210u128 & mask(bits);
+            pack_word(&mut bytes, order, lo, bits, v);
+            assert_eq!(unpack_word::<u128>(&bytes, order, lo, bits), v);
+            // Биты вне поля не изменились.
+            let mut other = [0xA5u8; 17];
+            pack_word(&mut other, order, lo, bits, v);
+            pack_word(&mut other, order, lo, bits, unpack_word::<u128>(&[0xA5u8; 17], order, lo, bits));
+            assert_eq!(other, [0xA5u8; 17]);
+        }
+    }
+    let mut bytes = [0u8; 3];
+    pack_word(&mut bytes, ByteOrder::Big, 4, 16, 0xABCDu16);
+    assert_eq!(bytes, [0x0A, 0xBC, 0xD0]);
+    let mut bytes = [0u8; 3];
+    pack_word(&mut bytes, ByteOrder::Little, 4, 16, 0xABCDu16);
+    assert_eq!(bytes, [0xD0, 0xBC, 0x0A]);
+}
+
+// Знаковые значения и реализации для bool и целых типов.
+#[test]
+fn test2() {
+    let mut bytes = [0u8; 4];
+    pack_word(&mut bytes, ByteOrder::Big, 3, 7, -20i32);
+    assert_eq!(unpack_word::<i32>(&bytes, ByteOrder::Big, 3, 7), -20);
+    assert_eq!(unpack_word::<u32>(&bytes, ByteOrder::Big, 3, 7), 108);
+
+    (-2i16).write_bits(&mut bytes, ByteOrder::Little, 11);
+    true.write_bits(&mut bytes, ByteOrder::Little, 31);
+    assert_eq!(i16::read_bits(&bytes, ByteOrder::Little, 11), Ok(-2));
+    assert_eq!(bool::read_bits(&bytes, ByteOrder::Little, 31), Ok(true));
+    assert_eq!(bool::read_bits(&bytes, ByteOrder::Little, 10), Ok(false));
+    assert_eq!(<u64 as BitPack>::BITS, 64);
+
+    assert!(word_fits(u128::MAX, 128) && !word_fits(u128::MAX, 127));
+    assert!(word_fits(i128::MIN, 128) && !word_fits(i128::MIN, 127));
+    assert!(word_fits(-1i32, 1) && !word_fits(1i32, 1) && word_fits(0i32, 1));
+    assert!(word_fits(0u8, 0) && !word_fits(1u8, 0) && !word_fits(-1i8, 0));
+}
@@ -0,0 +1,349 @@
+//! Упаковка с шаблоном ширин полей, повторяющимся по кругу.
+
+use alloc::vec::Vec;
+
+use crate::raw::{self, BitSource};
+use crate::{check_src, check_value, validate_widths, window_end, BitOrder, ByteOrder, Padding, RepackError, RepackOptions, Word};
+
+/// Разбивает битовую последовательность (по bits_in значащих бит из каждого
+/// эл-та src) на поля, ширины которых по кругу берутся из widths, например
+/// `[5, 6, 5]` для RGB565. Каждое поле записывается в отдельный эл-т результата.
+///
+/// Из всей последовательности используются первые bits_limit бит. Если их
+/// меньше, недостающие биты заполняются нулями.
+///
+/// # Arguments
+/// * `src` - срез с данными.
+/// * `bits_in` - кол-во значащих бит (справа) в каждом эл-те входного среза.
+/// * `widths` - ширины полей, повторяющиеся по кругу.
+/// * `bits_limit` - ограничение кол-ва всех входных значащих битов.
+///
+/// # Errors
+/// * [`RepackError::EmptyPattern`] - widths пуст.
+/// * [`RepackError::ZeroWidth`] - bits_in, одна из ширин или bits_limit равны нулю.
+/// * [`RepackError::BitsInTooLarge`] - bits_in больше размера T1.
+/// * [`RepackError::BitsOutTooLarge`] - одна из ширин больше размера T2.
+/// * [`RepackError::UnalignedBitsLimit`] - bits_limit заканчивается посреди поля.
+///
+/// # Examples
+///
+/// ```
+///     // RGB565: 5 бит красного, 6 бит зеленого, 5 бит синего.
+///     let pixels = [0b_11111_000000_10101u16, 0b_00001_111111_00000];
+///     let r: Vec<u8> = bits_rs::repack_pattern(&pixels, 16, &[5, 6, 5], 32).unwrap();
+///     assert_eq!(r, [31, 0, 21, 1, 63, 0]);
+/// ```
+pub fn repack_pattern<T1, T2>(src: &[T1], bits_in: usize, widths: &[usize], bits_limit: usize) -> Result<Vec<T2>, RepackError>
+where
+    T1: Word,
+    T2: Word,
+{
+    repack_pattern_with(src, bits_in, widths, bits_limit, RepackOptions::new())
+}
+
+/// То же, что и [`repack_pattern`], но с дополнительными параметрами упаковки
+/// (см. [`repack_with`](crate::repack_with)).
+///
+/// Порядок битов и байтов, знаковый режим и дополнение применяются к каждому
+/// полю с его шириной. Неполное последнее поле определяется
+/// [`RepackOptions::padding`]. [`RepackOptions::dst_offset`] не используется.
+///
+/// # Errors
+/// Те же, что и у [`repack_pattern`] и [`repack_into`](crate::repack_into).
+/// [`RepackError::UnalignedBitsLimit`] возвращается только при [`Padding::Reject`].
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{Padding, RepackOptions};
+///     let options = RepackOptions::new().padding(Padding::PadZeros).signed(true);
+///     let r: Vec<i8> = bits_rs::repack_pattern_with(&[0b_101_11101u8], 8, &[3, 5], 6, options).unwrap();
+///     assert_eq!(r, [-3, -4]);
+/// ```
+pub fn repack_pattern_with<T1, T2>(
+    src: &[T1],
+    bits_in: usize,
+    widths: &[usize],
+    bits_limit: usize,
+    options: RepackOptions,
+) -> Result<Vec<T2>, RepackError>
+where
+    T1: Word,
+    T2: Word,
+{
+    if widths.is_empty() {
+        return Err(RepackError::EmptyPattern);
+    }
+    if bits_limit < 1 {
+        return Err(RepackError::ZeroWidth { bits_in, bits_out: widths[0], bits_limit });
+    }
+    for &width in widths {
+        validate_widths::<T1, T2>(bits_in, width, bits_limit)?;
+    }
+
+    // Кол-во целых полей и кол-во бит в неполном последнем поле.
+    let cycle: usize = widths.iter().sum();
+    let mut rest = bits_limit % cycle;
+    let mut full = bits_limit / cycle * widths.len();
+    while rest >= widths[full % widths.len()] {
+        rest -= widths[full % widths.len()];
+        full += 1;
+    }
+    let padding = options.get_padding();
+    if rest > 0 && padding == Padding::Reject {
+        return Err(RepackError::UnalignedBitsLimit { bits_limit, bits_out: widths[full % widths.len()] });
+    }
+
+    check_src(src, bits_in, bits_limit, options)?;
+
+    let start = options.get_src_offset();
+    let index = (start / bits_in).min(src.len());
+    let (dst_order, dst_bytes) = (options.get_dst_order(), options.get_dst_byte_order());
+    let mut source = BitSource::new(src[index..].iter().copied(), bits_in, options.get_src_order())
+        .bytes(options.get_src_byte_order());
+    source.read(start - index * bits_in);
+
+    let signed = options.get_signed() && T2::SIGNED;
+    let field = |seq: u128, width: usize| -> T2 {
+        let v: T2 = raw::store_ordered(seq, width, dst_order, dst_bytes);
+        if signed && width < T2::BITS {
+            T2::from_raw(raw::sign_extend(v.to_raw(), width))
+        } else {
+            v
+        }
+    };
+
+    let mut dst = Vec::with_capacity(full + 1);
+    for &width in widths.iter().cycle().take(full) {
+        dst.push(field(source.read(width).0, width));
+    }
+    if rest > 0 {
+        let width = widths[full % widths.len()];
+        if let Some(seq) = raw::pad(source.read(rest).0, rest, width, padding, dst_order, dst_bytes) {
+            dst.push(field(seq, width));
+        }
+    }
+
+    Ok(dst)
+}
+
+/// Обратное к [`repack_pattern`] преобразование: эл-ты src содержат поля,
+/// ширины которых по кругу берутся из widths, а их биты упаковываются
+/// по bits_out бит в каждый эл-т результата.
+///
+/// Из всей последовательности полей используются первые bits_limit бит.
+/// Если их меньше, недостающие биты заполняются нулями.
+///
+/// # Errors
+/// * [`RepackError::EmptyPattern`] - widths пуст.
+/// * [`RepackError::ZeroWidth`] - одна из ширин, bits_out или bits_limit равны нулю.
+/// * [`RepackError::BitsInTooLarge`] - одна из ширин больше размера T1.
+/// * [`RepackError::BitsOutTooLarge`] - bits_out больше размера T2.
+/// * [`RepackError::UnalignedBitsLimit`] - bits_limit не делится на bits_out.
+///
+/// # Examples
+///
+/// ```
+///     let fields = [31u8, 0, 21, 1, 63, 0];
+///     let r: Vec<u16> = bits_rs::pack_pattern(&fields, &[5, 6, 5], 16, 32).unwrap();
+///     assert_eq!(r, [0b_11111_000000_10101, 0b_00001_111111_00000]);
+/// ```
+pub fn pack_pattern<T1, T2>(src: &[T1], widths: &[usize], bits_out: usize, bits_limit: usize) -> Result<Vec<T2>, RepackError>
+where
+    T1: Word,
+    T2: Word,
+{
+    pack_pattern_with(src, widths, bits_out, bits_limit, RepackOptions::new())
+}
+
+/// То же, что и [`pack_pattern`], но с дополнительными параметрами упаковки
+/// (см. [`repack_with`](crate::repack_with)).
+///
+/// Порядок битов и байтов, строгий и знаковый режимы применяются к каждому
+/// входному эл-ту с шириной его поля. [`RepackOptions::dst_offset`] не используется.
+///
+/// # Errors
+/// Те же, что и у [`pack_pattern`] и [`repack_into`](crate::repack_into).
+/// [`RepackError::UnalignedBitsLimit`] возвращается только при [`Padding::Reject`].
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{Padding, RepackOptions};
+///     let options = RepackOptions::new().padding(Padding::PadOnes).signed(true);
+///     let r: Vec<u8> = bits_rs::pack_pattern_with(&[-3i8, -8], &[3, 5], 8, 6, options).unwrap();
+///     assert_eq!(r, [0b_101_110_11]);
+/// ```
+pub fn pack_pattern_with<T1, T2>(
+    src: &[T1],
+    widths: &[usize],
+    bits_out: usize,
+    bits_limit: usize,
+    options: RepackOptions,
+) -> Result<Vec<T2>, RepackError>
+where
+    T1: Word,
+    T2: Word,
+{
+    if widths.is_empty() {
+        return Err(RepackError::EmptyPattern);
+    }
+    if bits_limit < 1 {
+        return Err(RepackError::ZeroWidth { bits_in: widths[0], bits_out, bits_limit });
+    }
+    for &width in widths {
+        validate_widths::<T1, T2>(width, bits_out, bits_limit)?;
+    }
+    let padding = options.get_padding();
+    if padding == Padding::Reject && !bits_limit.is_multiple_of(bits_out) {
+        return Err(RepackError::UnalignedBitsLimit { bits_limit, bits_out });
+    }
+
+    let start = options.get_src_offset();
+    let end = window_end(start, bits_limit)?;
+    if options.get_strict_length() {
+        let cycle: usize = widths.iter().sum();
+        let tail: usize = widths[..src.len() % widths.len()].iter().sum();
+        let available = src.len() / widths.len() * cycle + tail;
+        if available < end {
+            return Err(RepackError::SrcTooShort { available, required: end });
+        }
+    }
+
+    let mut sink = WordSink::new(bits_out, options);
+    let (src_order, src_bytes) = (options.get_src_order(), options.get_src_byte_order());
+    // Начало текущего входного эл-та в последовательности.
+    let mut pos = 0;
+    for (index, (&v, &width)) in src.iter().zip(widths.iter().cycle()).enumerate() {
+        if pos >= end {
+            break;
+        }
+        let next = pos + width;
+        if next > start {
+            check_value(index, v, width, options)?;
+            // Биты эл-та [from, to), попадающие в окно [start, end).
+            let from = start.saturating_sub(pos);
+            let to = width.min(end - pos);
+            let seq = raw::load_ordered(v, width, src_order, src_bytes) >> (width - to);
+            sink.push(seq & raw::mask(to - from), to - from);
+        }
+        pos = next;
+    }
+
+    // Недостающие биты - нули.
+    let mut missing = end - pos.clamp(start, end);
+    while missing > 0 {
+        let n = missing.min(128);
+        sink.push(0, n);
+        missing -= n;
+    }
+
+    Ok(sink.finish(padding))
+}
+
+// Собирает биты последовательности в эл-ты по bits значащих бит.
+struct WordSink<T> {
+    dst: Vec<T>,
+    bits: usize,
+    order: BitOrder,
+    bytes: ByteOrder,
+    signed: bool,
+    // Собранные биты текущего эл-та и их кол-во.
+    cur: u128,
+    len: usize,
+}
+
+impl<T: Word> WordSink<T> {
+    fn new(bits: usize, options: RepackOptions) -> Self {
+        WordSink {
+            dst: Vec::new(),
+            bits,
+            order: options.get_dst_order(),
+            bytes: options.get_dst_byte_order(),
+            signed: options.get_signed() && T::SIGNED && bits < T::BITS,
+            cur: 0,
+            len: 0,
+        }
+    }
+
+    // Добавляет n (не более 128) младших бит seq.
+    fn push(&mut self, seq: u128, mut n: usize) {
+        while n > 0 {
+            let take = n.min(self.bits - self.len);
+            n -= take;
+            self.cur = raw::shl(self.cur, take) | (raw::shr(seq, n) & raw::mask(take));
+            self.len += take;
+            if self.len == self.bits {
+                self.store(self.cur);
+                self.cur = 0;
+                self.len = 0;
+            }
+        }
+    }
+
+    fn store(&mut self, seq: u128) {
+        let v: T = raw::store_ordered(seq, self.bits, self.order, self.bytes);
+        self.dst.push(if self.signed { T::from_raw(raw::sign_extend(v.to_raw(), self.bits)) } else { v });
+    }
+
+    // Дополняет неполный последний эл-т согласно padding.
+    fn finish(mut self, padding: Padding) -> Vec<T> {
+        if self.len > 0 {
+            if let Some(seq) = raw::pad(self.cur, self.len, self.bits, padding, self.order, self.bytes) {
+                self.store(seq);
+            }
+        }
+        self.dst
+    }
+}
+
+// Разбиение и обратная упаковка совпадают с repack при одинаковых ширинах
+// и дают исходные поля при смешанных.
+#[test]
+fn test1() {
+    let src: Vec<u32> = (0..25u32).map(|i| i.wrapping_mul(2654435761)).collect();
+    for bits_out in [1, 7, 16, 32] {
+        let options = RepackOptions::new().src_order(BitOrder::Lsb0).src_offset(3).padding(Padding::PadOnes);
+        let e: Vec<u32> = crate::repack_with(&src, 32, bits_out, 700, options).unwrap();
+        assert_eq!(repack_pattern_with(&src, 32, &[bits_out], 700, options), Ok(e.clone()));
+        let options = RepackOptions::new().src_byte_order(ByteOrder::Little).src_offset(3).padding(Padding::PadOnes);
+        let e: Vec<u32> = crate::repack_with(&src, 32, bits_out, 700, options).unwrap();
+        assert_eq!(pack_pattern_with(&src, &[32], bits_out, 700, options), Ok(e));
+    }
+
+    let widths = [3, 5, 8, 13, 1];
+    let fields: Vec<u16> = repack_pattern(&src, 32, &widths, 25 * 30).unwrap();
+    assert_eq!(fields.len(), 25 * 5);
+    assert!(fields.iter().zip(widths.iter().cycle()).all(|(&f, &w)| f >> w == 0));
+    let packed: Vec<u32> = pack_pattern(&fields, &widths, 32, 25 * 30 / 32 * 32).unwrap();
+    assert_eq!(packed, src[..packed.len()]);
+}
+
+// Неполное последнее поле, нехватка бит и ошибки параметров.
+#[test]
+fn test2() {
+    let r = repack_pattern::<u8, u8>(&[0xFF], 8, &[], 8);
+    assert_eq!(r, Err(RepackError::EmptyPattern));
+    let r = repack_pattern::<u8, u8>(&[0xFF], 8, &[3, 9], 8);
+    assert_eq!(r, Err(RepackError::BitsOutTooLarge { bits_out: 9, size: 8 }));
+    let r = repack_pattern::<u8, u8>(&[0xFF], 8, &[3, 0], 8);
+    assert_eq!(r, Err(RepackError::ZeroWidth { bits_in: 8, bits_out: 0, bits_limit: 8 }));
+    let r = repack_pattern::<u8, u8>(&[0xFF], 8, &[3, 4], 8);
+    assert_eq!(r, Err(RepackError::UnalignedBitsLimit { bits_limit: 8, bits_out: 3 }));
+    let options = RepackOptions::new().padding(Padding::Truncate);
+    assert_eq!(repack_pattern_with(&[0xFFu8], 8, &[3, 4], 8, options), Ok(alloc::vec![7u8, 15]));
+    let options = RepackOptions::new().padding(Padding::PadZeros);
+    assert_eq!(repack_pattern_with(&[0xFFu8], 8, &[3, 4], 8, options), Ok(alloc::vec![7u8, 15, 4]));
+
+    let r = pack_pattern::<u8, u8>(&[1, 2], &[3, 9], 8, 8);
+    assert_eq!(r, Err(RepackError::BitsInTooLarge { bits_in: 9, size: 8 }));
+    let r = pack_pattern::<u8, u8>(&[1, 2], &[3, 5], 8, 12);
+    assert_eq!(r, Err(RepackError::UnalignedBitsLimit { bits_limit: 12, bits_out: 8 }));
+    assert_eq!(pack_pattern::<u8, u8>(&[1, 2], &[3, 5], 8, 16), Ok(alloc::vec![0b_0010_0010, 0]));
+    let options = RepackOptions::new().strict_length(true);
+    let r = pack_pattern_with::<u8, u8>(&[1, 2, 3], &[3, 5], 8, 16, options);
+    assert_eq!(r, Err(RepackError::SrcTooShort { available: 11, required: 16 }));
+    let options = RepackOptions::new().strict_values(true);
+    let r = pack_pattern_with::<u8, u8>(&[1, 32], &[3, 5], 8, 8, options);
+    assert_eq!(r, Err(RepackError::ValueTooWide { index: 1, value: 32, bits_in: 5 }));
+}
@@ -0,0 +1,325 @@
+//! Заранее подготовленная упаковка с фиксированными параметрами.
+
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+
+#[cfg(all(test, feature = "alloc"))]
+use crate::BitOrder;
+#[cfg(test)]
+use crate::ByteOrder;
+use crate::raw::{big_endian, load, load_ordered, store, store_ordered};
+use crate::{raw, repacked_len_with, validate, write_words, RepackError, RepackOptions, Word};
+
+/// Параметры [`repack_with`](crate::repack_with), проверенные один раз.
+///
+/// Подходит для многократной упаковки с одними и теми же bits_in, bits_out,
+/// bits_limit и параметрами упаковки: при каждом вызове проверяется только
+/// входной срез (в строгих режимах) и длина выходного.
+///
+/// Упакованная последовательность периодична: каждые НОК(bits_in, bits_out) бит
+/// выходные эл-ты вырезаются из входных одинаково. Если входные эл-ты периода
+/// помещаются в 64 бита, план заранее вычисляет таблицу сдвигов выходных
+/// эл-тов внутри периода: при упаковке период целиком собирается в одно число,
+/// и каждый выходной эл-т получается из него одним сдвигом и маской, без
+/// сдвигового регистра и ветвлений. Для более длинных периодов и периодов
+/// из одного выходного эл-та используется сдвиговый регистр, как в
+/// [`repack`](crate::repack).
+///
+/// Таблица хранится в самом плане, без выделения памяти, так что план
+/// копируется дешево и может передаваться между потоками.
+///
+/// Результат совпадает с результатом [`repack_with`](crate::repack_with).
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{RepackOptions, RepackPlan};
+///     let plan = RepackPlan::<u16, u8>::new(3, 2, 6, RepackOptions::new()).unwrap();
+///     assert_eq!(plan.len(), 3);
+///     let mut dst = [0u8; 3];
+///     assert_eq!(plan.apply_into(&[5, 5], &mut dst), Ok(3));
+///     assert_eq!(dst, [0b_10, 0b_11, 0b_01]);
+///     assert_eq!(plan.apply_into(&[7, 0], &mut dst), Ok(3));
+///     assert_eq!(dst, [0b_11, 0b_10, 0b_00]);
+/// ```
+#[derive(Debug)]
+pub struct RepackPlan<T1, T2> {
+    bits_in: usize,
+    bits_out: usize,
+    bits_limit: usize,
+    options: RepackOptions,
+    len: usize,
+    table: Option<ShiftTable>,
+    _marker: PhantomData<fn(&[T1]) -> T2>,
+}
+
+// Производные Clone и Copy требовали бы Clone и Copy от T1 и T2.
+impl<T1, T2> Clone for RepackPlan<T1, T2> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T1, T2> Copy for RepackPlan<T1, T2> {}
+
+impl<T1: Word, T2: Word> RepackPlan<T1, T2> {
+    /// Проверяет параметры упаковки.
+    ///
+    /// # Arguments
+    /// * `bits_in` - кол-во значащих бит (справа) в каждом эл-те входного среза.
+    /// * `bits_out` - кол-во значащих бит (справа) в каждом эл-те выходного среза.
+    /// * `bits_limit` - ограничение кол-ва всех входных значащих битов.
+    /// * `options` - параметры упаковки.
+    ///
+    /// # Errors
+    /// Те же, что и у [`repack_with`](crate::repack_with), кроме ошибок,
+    /// зависящих от входного среза.
+    pub fn new(bits_in: usize, bits_out: usize, bits_limit: usize, options: RepackOptions) -> Result<Self, RepackError> {
+        validate::<T1, T2>(bits_in, bits_out, bits_limit, options)?;
+
+        // Целые выходные эл-ты начинаются с того же бита входной
+        // последовательности, что и в write_words: после дописанного начала
+        // первого эл-та, если dst_offset не кратен bits_out.
+        let dst_offset = options.get_dst_offset();
+        let lead = dst_offset % bits_out;
+        let mut pos = options.get_src_offset();
+        if lead > 0 && dst_offset / bits_out < (dst_offset + bits_limit) / bits_out {
+            pos += bits_out - lead;
+        }
+
+        Ok(RepackPlan {
+            bits_in,
+            bits_out,
+            bits_limit,
+            options,
+            len: repacked_len_with(bits_out, bits_limit, options),
+            table: ShiftTable::new(bits_in, bits_out, pos % bits_in),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Кол-во значащих бит во входном эл-те.
+    pub fn bits_in(&self) -> usize {
+        self.bits_in
+    }
+
+    /// Кол-во значащих бит в выходном эл-те.
+    pub fn bits_out(&self) -> usize {
+        self.bits_out
+    }
+
+    /// Ограничение кол-ва входных бит.
+    pub fn bits_limit(&self) -> usize {
+        self.bits_limit
+    }
+
+    /// Параметры упаковки.
+    pub fn options(&self) -> RepackOptions {
+        self.options
+    }
+
+    /// Кол-во эл-тов результата (см. [`repacked_len_with`]).
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Пуст ли результат.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Упаковывает src, как [`repack_with`](crate::repack_with).
+    ///
+    /// # Errors
+    /// Ошибки строгих режимов и знакового режима (см. [`repack_into`](crate::repack_into)).
+    #[cfg(feature = "alloc")]
+    pub fn apply(&self, src: &[T1]) -> Result<Vec<T2>, RepackError> {
+        let mut dst = vec![T2::from_raw(0); self.len];
+        self.apply_into(src, &mut dst)?;
+        Ok(dst)
+    }
+
+    /// Упаковывает src в начало dst, как [`repack_into_with`](crate::repack_into_with).
+    ///
+    /// # Errors
+    /// * [`RepackError::DstTooSmall`] - в dst меньше эл-тов, чем нужно.
+    /// * Ошибки строгих режимов и знакового режима (см. [`repack_into`](crate::repack_into)).
+    pub fn apply_into(&self, src: &[T1], dst: &mut [T2]) -> Result<usize, RepackError> {
+        let (bits_in, bits_out, options) = (self.bits_in, self.bits_out, self.options);
+        write_words(src, bits_in, dst, bits_out, self.bits_limit, options, |src, pos, dst| match &self.table {
+            Some(table) if pos % bits_in == table.skip => {
+                let src = &src[(pos / bits_in).min(src.len())..];
+                let (src_order, dst_order) = (options.get_src_order(), options.get_dst_order());
+                let (src_bytes, dst_bytes) = (options.get_src_byte_order(), options.get_dst_byte_order());
+                // Для порядка Big цикл собирается без перестановки байтов.
+                if big_endian(src_bytes) && big_endian(dst_bytes) {
+                    table.apply(src, dst, |v| load(v, bits_in, src_order), |seq| store(seq, bits_out, dst_order));
+                } else {
+                    table.apply(
+                        src,
+                        dst,
+                        |v| load_ordered(v, bits_in, src_order, src_bytes),
+                        |seq| store_ordered(seq, bits_out, dst_order, dst_bytes),
+                    );
+                }
+            }
+            _ => raw::repack_words(src, bits_in, pos, dst, bits_out, options),
+        })
+    }
+}
+
+// Таблица сдвигов для периода, кратного НОК(bits_in, bits_out) бит и
+// начинающегося с бита skip первого входного эл-та.
+//
+// Период читается из need входных эл-тов (на один больше при
+// невыровненном skip), склеенных в одно число, а выходной эл-т j периода -
+// его биты, сдвинутые вправо на rsh[j] и обрезанные маской.
+#[derive(Debug, Clone, Copy)]
+struct ShiftTable {
+    bits_in: usize,
+    skip: usize,
+    // Кол-во входных эл-тов, на которое сдвигается период.
+    step: usize,
+    need: usize,
+    outs: usize,
+    mask: u64,
+    rsh: [u8; 64],
+}
+
+impl ShiftTable {
+    // None, если входные эл-ты периода не помещаются в 64 бита или период
+    // состоит из одного выходного эл-та: тогда сдвиговый регистр не медленнее.
+    fn new(bits_in: usize, bits_out: usize, skip: usize) -> Option<Self> {
+        let (mut a, mut b) = (bits_in, bits_out);
+        while b > 0 {
+            (a, b) = (b, a % b);
+        }
+        let lcm = bits_in / a * bits_out;
+        let need = |period: usize| (skip + period).div_ceil(bits_in);
+        // Чем длиннее период, тем меньше накладных расходов на каждый.
+        let mut period = lcm;
+        while need(period + lcm) * bits_in <= 64 {
+            period += lcm;
+        }
+        if need(period) * bits_in > 64 || period / bits_out < 2 {
+            return None;
+        }
+
+        let mut table = ShiftTable {
+            bits_in,
+            skip,
+            step: period / bits_in,
+            need: need(period),
+            outs: period / bits_out,
+            mask: raw::mask(bits_out) as u64,
+            rsh: [0; 64],
+        };
+        for j in 0..table.outs {
+            table.rsh[j] = (table.need * bits_in - skip - (j + 1) * bits_out) as u8;
+        }
+        Some(table)
+    }
+
+    // Заполняет dst по таблице битами src, начиная с бита skip src[0].
+    // За концом src последовательность дополняется нулями.
+    #[inline(always)]
+    fn apply<T1: Word, T2: Word>(&self, src: &[T1], dst: &mut [T2], load: impl Fn(T1) -> u128, store: impl Fn(u128) -> T2) {
+        let (bits_in, need) = (self.bits_in, self.need);
+        let rsh = &self.rsh[..self.outs];
+        // Входные эл-ты периода, начинающегося с src[base], склеенные в одно
+        // число. need * bits_in <= 64, поэтому сдвиг на bits_in возможен
+        // только при need > 1 и не переполняет u64.
+        let period = |base: usize| match src.get(base..base + need) {
+            Some(window) => window[1..].iter().fold(load(window[0]) as u64, |seq, &v| (seq << bits_in) | load(v) as u64),
+            None => (base..base + need).fold(0, |seq, i| (seq << bits_in.min(63)) | src.get(i).map_or(0, |&v| load(v) as u64)),
+        };
+
+        let mut chunks = dst.chunks_exact_mut(self.outs);
+        let mut base = 0;
+        for out in &mut chunks {
+            let seq = period(base);
+            for (w, &rsh) in out.iter_mut().zip(rsh) {
+                *w = store(((seq >> rsh) & self.mask) as u128);
+            }
+            base += self.step;
+        }
+        let out = chunks.into_remainder();
+        if !out.is_empty() {
+            let seq = period(base);
+            for (w, &rsh) in out.iter_mut().zip(rsh) {
+                *w = store(((seq >> rsh) & self.mask) as u128);
+            }
+        }
+    }
+}
+
+// Результат совпадает с repack_with для разных сочетаний ширин, смещений и политик.
+#[test]
+#[cfg(feature = "alloc")]
+fn test1() {
+    let src: Vec<u128> = (0..40u128).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15_F39C_C060_5CED_C834)).collect();
+    let orders = [BitOrder::Msb0, BitOrder::Lsb0];
+    for (bits_in, bits_out) in [(1, 1), (3, 5), (8, 12), (13, 7), (64, 64), (100, 29), (127, 128), (128, 8), (5, 128)] {
+        for (src_offset, dst_offset) in [(0, 0), (5, 3), (17, 130)] {
+            for (&src_order, &dst_order) in orders.iter().zip(orders.iter().rev()) {
+                let options = RepackOptions::new()
+                    .src_order(src_order)
+                    .dst_order(dst_order)
+                    .src_offset(src_offset)
+                    .dst_offset(dst_offset)
+                    .padding(crate::Padding::PadOnes);
+                let bits_limit = 40 * bits_in - 3;
+                let plan = RepackPlan::<u128, u128>::new(bits_in, bits_out, bits_limit, options).unwrap();
+                let e: Vec<u128> = crate::repack_with(&src, bits_in, bits_out, bits_limit, options).unwrap();
+                assert_eq!(plan.apply(&src), Ok(e), "{} {} {:?}", bits_in, bits_out, options);
+            }
+        }
+    }
+}
+
+// Ошибки параметров возвращаются при создании плана, ошибки входных данных - при упаковке.
+#[test]
+fn test2() {
+    let r = RepackPlan::<u8, u8>::new(3, 4, 6, RepackOptions::new());
+    assert_eq!(r.unwrap_err(), RepackError::UnalignedBitsLimit { bits_limit: 6, bits_out: 4 });
+
+    let plan = RepackPlan::<u8, u8>::new(3, 3, 6, RepackOptions::new().strict_values(true)).unwrap();
+    let mut dst = [0u8; 2];
+    let r = plan.apply_into(&[1, 9], &mut dst);
+    assert_eq!(r, Err(RepackError::ValueTooWide { index: 1, value: 9, bits_in: 3 }));
+    let mut dst = [0u8; 1];
+    assert_eq!(plan.apply_into(&[1, 2], &mut dst), Err(RepackError::DstTooSmall { len: 1, required: 2 }));
+
+    fn shared<T: Send + Sync + Copy>(_: &T) {}
+    shared(&plan);
+}
+
+// Таблица сдвигов строится только для коротких периодов, а упаковка по ней
+// совпадает с repack_into_with при любых порядках бит и байтов и смещениях.
+#[test]
+fn test3() {
+    assert!(ShiftTable::new(12, 8, 0).is_some());
+    assert!(ShiftTable::new(8, 3, 5).is_some());
+    assert!(ShiftTable::new(13, 7, 0).is_none());
+    assert!(ShiftTable::new(32, 64, 0).is_none());
+
+    let src: [u16; 24] = core::array::from_fn(|i| (i as u16).wrapping_mul(40503) ^ 0x5A5A);
+    for (bits_in, bits_out) in [(12, 8), (16, 8), (8, 3), (4, 12), (10, 6)] {
+        for (src_offset, dst_offset) in [(0, 0), (3, 0), (0, 5), (7, 9)] {
+            for order in [ByteOrder::Big, ByteOrder::Little] {
+                let options = RepackOptions::new()
+                    .src_offset(src_offset)
+                    .dst_offset(dst_offset)
+                    .src_byte_order(order)
+                    .padding(crate::Padding::PadZeros);
+                let bits_limit = 20 * bits_in - 1;
+                let plan = RepackPlan::<u16, u16>::new(bits_in, bits_out, bits_limit, options).unwrap();
+                let (mut e, mut dst) = ([0u16; 64], [0u16; 64]);
+                crate::repack_into_with(&src, bits_in, &mut e, bits_out, bits_limit, options).unwrap();
+                plan.apply_into(&src, &mut dst).unwrap();
+                assert_eq!(dst, e, "{} {} {:?}", bits_in, bits_out, options);
+            }
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! Низкоуровневые операции над битовой последовательностью.
+//!
+//! Внутри крейта биты последовательности передаются как `u128`, в котором
+//! первый бит последовательности занимает старшую из значащих позиций,
+//! независимо от порядка битов в исходных эл-тах.
+
+use crate::{BitOrder, ByteOrder, Padding, RepackOptions, Word};
+
+/// Маска младших `bits` бит.
+#[inline]
+pub(crate) const fn mask(bits: usize) -> u128 {
+    if bits >= 128 {
+        u128::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+/// Сдвиг влево, который при `n >= 128` дает ноль вместо переполнения.
+#[inline]
+pub(crate) const fn shl(v: u128, n: usize) -> u128 {
+    if n >= 128 {
+        0
+    } else {
+        v << n
+    }
+}
+
+/// Сдвиг вправо, который при `n >= 128` дает ноль вместо переполнения.
+#[inline]
+pub(crate) const fn shr(v: u128, n: usize) -> u128 {
+    if n >= 128 {
+        0
+    } else {
+        v >> n
+    }
+}
+
+/// Разворачивает порядок младших `bits` бит.
+#[inline]
+pub(crate) const fn reverse(v: u128, bits: usize) -> u128 {
+    v.reverse_bits() >> (128 - bits)
+}
+
+/// Расширяет знак младших bits бит на все 128 бит.
+#[inline]
+pub(crate) const fn sign_extend(v: u128, bits: usize) -> u128 {
+    let v = v & mask(bits);
+    if bits < 128 && v >> (bits - 1) != 0 {
+        v | !mask(bits)
+    } else {
+        v
+    }
+}
+
+/// Помещается ли эл-т в bits бит дополнительного кода.
+#[inline]
+pub(crate) fn fits_signed<T: Word>(v: T, bits: usize) -> bool {
+    let raw = v.to_raw();
+    if T::SIGNED {
+        sign_extend(raw, bits) == raw
+    } else {
+        bits >= 128 || raw >> (bits - 1) == 0
+    }
+}
+
+/// Значение эл-та для сообщений об ошибках. Значения `u128`, не помещающиеся
+/// в `i128`, заменяются на `i128::MAX`.
+pub(crate) fn value_of<T: Word>(v: T) -> i128 {
+    let raw = v.to_raw();
+    if T::SIGNED {
+        raw as i128
+    } else {
+        raw.min(i128::MAX as u128) as i128
+    }
+}
+
+/// Значащие биты эл-та в порядке последовательности.
+#[inline]
+pub(crate) fn load<T: Word>(v: T, bits: usize, order: BitOrder) -> u128 {
+    let v = v.to_raw() & mask(bits);
+    match order {
+        BitOrder::Msb0 => v,
+        BitOrder::Lsb0 => reverse(v, bits),
+    }
+}
+
+/// Эл-т из `bits` бит последовательности.
+#[inline]
+pub(crate) fn store<T: Word>(seq: u128, bits: usize, order: BitOrder) -> T {
+    let v = match order {
+        BitOrder::Msb0 => seq,
+        BitOrder::Lsb0 => reverse(seq, bits),
+    };
+    T::from_raw(v)
+}
+
+/// Значащие биты эл-та в порядке последовательности с учетом порядка байтов.
+#[inline]
+pub(crate) fn load_ordered<T: Word>(v: T, bits: usize, order: BitOrder, bytes: ByteOrder) -> u128 {
+    if big_endian(bytes) {
+        return load(v, bits, order);
+    }
+    let v = little_to_seq(v.to_raw() & mask(bits), bits);
+    match order {
+        BitOrder::Msb0 => v,
+        BitOrder::Lsb0 => reverse(v, bits),
+    }
+}
+
+/// Эл-т из `bits` бит последовательности с учетом порядка байтов.
+#[inline]
+pub(crate) fn store_ordered<T: Word>(seq: u128, bits: usize, order: BitOrder, bytes: ByteOrder) -> T {
+    if big_endian(bytes) {
+        return store(seq, bits, order);
+    }
+    let v = match order {
+        BitOrder::Msb0 => seq,
+        BitOrder::Lsb0 => reverse(seq, bits),
+    };
+    T::from_raw(seq_to_little(v, bits))
+}
+
+/// Переставляет байты младших bits бит так, чтобы первым (старшим) шел
+/// младший байт, а последним - старший, возможно неполный.
+#[inline]
+pub(crate) const fn little_to_seq(v: u128, bits: usize) -> u128 {
+    let v = v & mask(bits);
+    if bits.is_multiple_of(8) {
+        return v.swap_bytes() >> (128 - bits);
+    }
+    let (mut seq, mut v, mut left) = (0, v, bits);
+    while left > 8 {
+        seq = (seq << 8) | (v & 0xFF);
+        v >>= 8;
+        left -= 8;
+    }
+    (seq << left) | v
+}
+
+/// Обратная перестановка к [`little_to_seq`].
+#[inline]
+pub(crate) const fn seq_to_little(seq: u128, bits: usize) -> u128 {
+    let seq = seq & mask(bits);
+    if bits.is_multiple_of(8) {
+        return seq.swap_bytes() >> (128 - bits);
+    }
+    let (mut v, mut left, mut shift) = (0, bits, 0);
+    while left > 8 {
+        left -= 8;
+        v |= ((seq >> left) & 0xFF) << shift;
+        shift += 8;
+    }
+    v | ((seq & mask(left)) << shift)
+}
+
+/// Идет ли в порядке order первым старший байт.
+#[inline]
+pub(crate) const fn big_endian(order: ByteOrder) -> bool {
+    match order {
+        ByteOrder::Big => true,
+        ByteOrder::Little => false,
+        ByteOrder::Native => cfg!(target_endian = "big"),
+    }
+}
+
+/// Эл-т из T::BITS / 8 байтов bytes в порядке order.
+#[cfg(feature = "std")]
+pub(crate) fn word_from_bytes<T: Word>(bytes: &[u8], order: ByteOrder) -> T {
+    let raw = if big_endian(order) {
+        bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u128)
+    } else {
+        bytes.iter().rev().fold(0, |acc, &b| (acc << 8) | b as u128)
+    };
+    T::from_raw(raw)
+}
+
+/// Байты эл-та в порядке order.
+#[cfg(feature = "std")]
+pub(crate) fn word_to_bytes<T: Word>(v: T, order: ByteOrder) -> impl Iterator<Item = u8> {
+    let raw = v.to_raw();
+    let size = T::BITS / 8;
+    let big = big_endian(order);
+    (0..size).map(move |i| {
+        let k = if big { size - 1 - i } else { i };
+        (raw >> (8 * k)) as u8
+    })
+}
+
+/// Дополняет len бит последовательности до bits bits согласно padding.
+/// Возвращает None, если неполный эл-т не выдается (Reject, Truncate).
+pub(crate) fn pad(seq: u128, len: usize, bits: usize, padding: Padding, order: BitOrder, bytes: ByteOrder) -> Option<u128> {
+    let pattern = match padding {
+        Padding::Reject | Padding::Truncate => return None,
+        Padding::PadZeros => 0,
+        Padding::PadOnes => u128::MAX,
+        Padding::PadWith(pattern) => load_ordered(pattern, bits, order, bytes),
+    };
+    let free = bits - len;
+    Some(shl(seq, free) | (pattern & mask(free)))
+}
+
+/// n (не более 128) бит последовательности, начиная с бита pos.
+/// За концом среза последовательность дополняется нулями.
+pub(crate) fn read_at<T: Word>(src: &[T], bits: usize, order: BitOrder, bytes: ByteOrder, pos: usize, n: usize) -> u128 {
+    let index = (pos / bits).min(src.len());
+    let mut source = BitSource::new(src[index..].iter().copied(), bits, order).bytes(bytes);
+    source.read(pos - index * bits);
+    source.read(n).0
+}
+
+/// Читает биты из последовательности эл-тов по bits значащих бит в каждом.
+/// За концом последовательности биты дополняются нулями.
+#[derive(Debug, Clone)]
+pub(crate) struct BitSource<I> {
+    iter: I,
+    bits: usize,
+    order: BitOrder,
+    bytes: ByteOrder,
+    // Текущий эл-т и кол-во еще не прочитанных (младших) бит в нем.
+    cur: u128,
+    avail: usize,
+}
+
+impl<T: Word, I: Iterator<Item = T>> BitSource<I> {
+    pub(crate) fn new(iter: I, bits: usize, order: BitOrder) -> Self {
+        BitSource {
+            iter,
+            bits,
+            order,
+            bytes: ByteOrder::Big,
+            cur: 0,
+            avail: 0,
+        }
+    }
+
+    /// Задает порядок байтов эл-тов (по умолчанию Big).
+    pub(crate) fn bytes(mut self, bytes: ByteOrder) -> Self {
+        self.bytes = bytes;
+        self
+    }
+
+    /// Следующие n (не более 128) бит последовательности и кол-во
+    /// из них, действительно взятых из эл-тов (остальные - нули).
+    #[inline]
+    pub(crate) fn read(&mut self, n: usize) -> (u128, usize) {
+        let mut out = 0;
+        let mut need = n;
+        while need > 0 {
+            if self.avail == 0 {
+                match self.iter.next() {
+                    Some(v) => self.cur = load_ordered(v, self.bits, self.order, self.bytes),
+                    None => return (shl(out, need), n - need),
+                }
+                self.avail = self.bits;
+            }
+            let take = need.min(self.avail);
+            self.avail -= take;
+            let chunk = shr(self.cur, self.avail) & mask(take);
+            out = shl(out, take) | chunk;
+            need -= take;
+        }
+        (out, n)
+    }
+
+    /// Итератор эл-тов.
+    pub(crate) fn get_ref(&self) -> &I {
+        &self.iter
+    }
+
+    /// Кол-во бит, оставшихся в последовательности: (нижняя граница, верхняя граница).
+    pub(crate) fn bits_hint(&self) -> (usize, Option<usize>) {
+        let (lo, hi) = self.iter.size_hint();
+        let lo = lo.saturating_mul(self.bits).saturating_add(self.avail);
+        let hi = hi
+            .and_then(|hi| hi.checked_mul(self.bits))
+            .and_then(|hi| hi.checked_add(self.avail));
+        (lo, hi)
+    }
+}
+
+/// Заполняет dst битами из src, начиная с бита pos. Если бит в src
+/// не хватает, последовательность дополняется нулями.
+///
+/// Из options берутся только порядки битов и байтов.
+pub(crate) fn repack_words<T1: Word, T2: Word>(
+    src: &[T1],
+    bits_in: usize,
+    pos: usize,
+    dst: &mut [T2],
+    bits_out: usize,
+    options: RepackOptions,
+) {
+    let index = (pos / bits_in).min(src.len());
+    let skip = pos - index * bits_in;
+    let src = &src[index..];
+    let (src_order, dst_order) = (options.get_src_order(), options.get_dst_order());
+    let (src_bytes, dst_bytes) = (options.get_src_byte_order(), options.get_dst_byte_order());
+
+    if bits_in + bits_out <= 128 {
+        // Для порядка Big цикл собирается без перестановки байтов.
+        if big_endian(src_bytes) && big_endian(dst_bytes) {
+            shift_words(src, bits_in, skip, dst, bits_out, |v| load(v, bits_in, src_order), |seq| {
+                store(seq, bits_out, dst_order)
+            });
+        } else {
+            shift_words(
+                src,
+                bits_in,
+                skip,
+                dst,
+                bits_out,
+                |v| load_ordered(v, bits_in, src_order, src_bytes),
+                |seq| store_ordered(seq, bits_out, dst_order, dst_bytes),
+            );
+        }
+    } else {
+        let mut source = BitSource::new(src.iter().copied(), bits_in, src_order).bytes(src_bytes);
+        source.read(skip);
+        for w in dst.iter_mut() {
+            *w = store_ordered(source.read(bits_out).0, bits_out, dst_order, dst_bytes);
+        }
+    }
+}
+
+// Сдвиговый регистр: в acc всегда меньше bits_out бит до добавления
+// очередного эл-та, поэтому bits_in + bits_out бит в нем помещаются.
+#[inline(always)]
+fn shift_words<T1: Word, T2: Word>(
+    src: &[T1],
+    bits_in: usize,
+    skip: usize,
+    dst: &mut [T2],
+    bits_out: usize,
+    load: impl Fn(T1) -> u128,
+    store: impl Fn(u128) -> T2,
+) {
+    let mut acc = 0u128;
+    let mut acc_len = 0;
+    let mut src = src.iter();
+    if skip > 0 {
+        if let Some(&v) = src.next() {
+            acc_len = bits_in - skip;
+            acc = load(v) & mask(acc_len);
+        }
+    }
+    for w in dst.iter_mut() {
+        while acc_len < bits_out {
+            let v = match src.next() {
+                Some(&v) => load(v),
+                None => 0,
+            };
+            acc = (acc << bits_in) | v;
+            acc_len += bits_in;
+        }
+        acc_len -= bits_out;
+        *w = store((acc >> acc_len) & mask(bits_out));
+    }
+}
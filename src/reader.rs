@@ -0,0 +1,345 @@
+//! Чтение полей переменной ширины из битового потока.
+
+use crate::raw::{load, mask, reverse, shl};
+use crate::{BitOrder, ReadError, Word};
+
+/// Источник байтов для [`BitReader`].
+///
+/// Реализован для `&[u8]`, а при включенном `std` - для [`IoSource`].
+pub trait ByteSource {
+    /// Следующий байт или None, если поток закончился.
+    fn next_byte(&mut self) -> Result<Option<u8>, ReadError>;
+
+    /// Кол-во оставшихся байтов, если оно известно заранее.
+    fn bytes_left(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl ByteSource for &[u8] {
+    fn next_byte(&mut self) -> Result<Option<u8>, ReadError> {
+        match self.split_first() {
+            Some((&b, rest)) => {
+                *self = rest;
+                Ok(Some(b))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn bytes_left(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+/// Источник байтов из [`std::io::Read`].
+///
+/// Байты читаются по одному, поэтому небуферизованные источники
+/// (файлы, сокеты) лучше обернуть в [`std::io::BufReader`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoSource<R> {
+    inner: R,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> IoSource<R> {
+    /// Источник, читающий байты из inner.
+    pub fn new(inner: R) -> Self {
+        IoSource { inner }
+    }
+
+    /// Исходный поток. Байты, уже прочитанные в буфер [`BitReader`], теряются.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> ByteSource for IoSource<R> {
+    fn next_byte(&mut self) -> Result<Option<u8>, ReadError> {
+        let mut b = [0u8];
+        loop {
+            match self.inner.read(&mut b) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(b[0])),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(ReadError::Io(e.kind())),
+            }
+        }
+    }
+}
+
+/// Читает из потока байтов поля произвольной ширины, одно за другим.
+///
+/// В порядке [`BitOrder::Msb0`] биты каждого байта берутся начиная со
+/// старшего, и первый прочитанный бит поля становится его старшим битом
+/// (как в [`repack`](crate::repack)). В порядке [`BitOrder::Lsb0`] биты
+/// берутся начиная с младшего, и первый прочитанный бит становится младшим
+/// битом поля (как в DEFLATE).
+///
+/// Если запрошенных бит в потоке не хватает, возвращается
+/// [`ReadError::UnexpectedEnd`], а позиция не меняется (кроме
+/// [`BitReader::skip`]).
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::BitReader;
+///     // Тип (3 бита), длина (13 бит), затем длина 7-битных значений.
+///     let data = [0b_1010_0000, 0b_0000_0010, 0b_0000_0110, 0b_0000_1111, 0b_1000_0000];
+///     let mut reader = BitReader::new(&data[..]);
+///     assert_eq!(reader.read_bits::<u8>(3), Ok(5));
+///     let len = reader.read_bits::<u16>(13).unwrap();
+///     assert_eq!(len, 2);
+///     let values: Vec<u8> = (0..len).map(|_| reader.read_bits(7).unwrap()).collect();
+///     assert_eq!(values, [3, 3]);
+///     assert_eq!(reader.position(), 30);
+///     assert_eq!(reader.remaining(), Some(10));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BitReader<S> {
+    source: S,
+    order: BitOrder,
+    // Прочитанные из источника, но еще не использованные байты; из первого
+    // из них уже взято bit бит. 17 байтов хватает на 128 бит с любого бита.
+    buf: [u8; 17],
+    buf_len: usize,
+    bit: usize,
+    // Кол-во прочитанных бит.
+    pos: usize,
+}
+
+impl<S: ByteSource> BitReader<S> {
+    /// Читатель потока source в порядке [`BitOrder::Msb0`].
+    pub fn new(source: S) -> Self {
+        Self::with_order(source, BitOrder::Msb0)
+    }
+
+    /// То же, что и [`BitReader::new`], но с заданным порядком битов.
+    pub fn with_order(source: S, order: BitOrder) -> Self {
+        BitReader {
+            source,
+            order,
+            buf: [0; 17],
+            buf_len: 0,
+            bit: 0,
+            pos: 0,
+        }
+    }
+
+    /// Порядок битов.
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    /// Кол-во прочитанных (и пропущенных) бит.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Кол-во оставшихся бит или None, если размер источника неизвестен.
+    pub fn remaining(&self) -> Option<usize> {
+        self.source.bytes_left().map(|n| n * 8 + self.buffered())
+    }
+
+    /// Читает поле из n бит.
+    ///
+    /// # Errors
+    /// * [`ReadError::BitsTooLarge`] - n больше размера T или 128 бит
+    ///   (для пользовательских реализаций [`Word`] шире 128 бит).
+    /// * [`ReadError::UnexpectedEnd`] - в потоке меньше n бит.
+    pub fn read_bits<T: Word>(&mut self, n: usize) -> Result<T, ReadError> {
+        let v = self.peek_bits(n)?;
+        self.consume(n);
+        Ok(v)
+    }
+
+    /// Читает один бит.
+    pub fn read_bool(&mut self) -> Result<bool, ReadError> {
+        Ok(self.read_bits::<u8>(1)? != 0)
+    }
+
+    /// Поле из n бит, как [`BitReader::read_bits`], но без сдвига позиции.
+    pub fn peek_bits<T: Word>(&mut self, n: usize) -> Result<T, ReadError> {
+        if n > T::BITS.min(128) {
+            return Err(ReadError::BitsTooLarge { bits: n, size: T::BITS.min(128) });
+        }
+        self.fill(n)?;
+
+        // Биты в порядке потока, первый - старший.
+        let mut seq = 0;
+        let mut need = n;
+        let mut off = self.bit;
+        for &b in &self.buf[..self.buf_len] {
+            if need == 0 {
+                break;
+            }
+            let take = need.min(8 - off);
+            seq = shl(seq, take) | ((load(b, 8, self.order) >> (8 - off - take)) & mask(take));
+            need -= take;
+            off = 0;
+        }
+
+        let v = match self.order {
+            BitOrder::Lsb0 if n > 0 => reverse(seq, n),
+            _ => seq,
+        };
+        Ok(T::from_raw(v))
+    }
+
+    /// Пропускает n бит.
+    ///
+    /// # Errors
+    /// * [`ReadError::UnexpectedEnd`] - в потоке меньше n бит. В этом случае
+    ///   позиция переходит в конец потока.
+    pub fn skip(&mut self, n: usize) -> Result<(), ReadError> {
+        let position = self.pos;
+        let mut left = n;
+        while left > 0 {
+            let take = left.min(128);
+            if let Err(e) = self.fill(take) {
+                let available = n - left + self.buffered();
+                self.consume(self.buffered());
+                return Err(match e {
+                    ReadError::UnexpectedEnd { .. } => ReadError::UnexpectedEnd { position, requested: n, available },
+                    e => e,
+                });
+            }
+            self.consume(take);
+            left -= take;
+        }
+        Ok(())
+    }
+
+    /// Пропускает биты до начала следующего байта потока.
+    pub fn align_to_byte(&mut self) {
+        if self.bit > 0 {
+            self.consume(8 - self.bit);
+        }
+    }
+
+    // Кол-во бит в буфере.
+    fn buffered(&self) -> usize {
+        self.buf_len * 8 - self.bit
+    }
+
+    // Дочитывает в буфер байты, пока в нем не окажется n (не более 128) бит.
+    fn fill(&mut self, n: usize) -> Result<(), ReadError> {
+        while self.buffered() < n {
+            match self.source.next_byte()? {
+                Some(b) => {
+                    self.buf[self.buf_len] = b;
+                    self.buf_len += 1;
+                }
+                None => {
+                    return Err(ReadError::UnexpectedEnd {
+                        position: self.pos,
+                        requested: n,
+                        available: self.buffered(),
+                    })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Убирает из буфера n бит.
+    fn consume(&mut self, n: usize) {
+        let total = self.bit + n;
+        let k = total / 8;
+        self.buf.copy_within(k..self.buf_len, 0);
+        self.buf_len -= k;
+        self.bit = total % 8;
+        self.pos += n;
+    }
+}
+
+// Поля разной ширины читаются так же, как их читает repack.
+#[test]
+#[cfg(feature = "alloc")]
+fn test1() {
+    let data: alloc::vec::Vec<u8> = (0..40u8).map(|i| i.wrapping_mul(151)).collect();
+    for order in [BitOrder::Msb0, BitOrder::Lsb0] {
+        let options = crate::RepackOptions::new().src_order(order).dst_order(order);
+        let mut reader = BitReader::with_order(&data[..], order);
+        let mut pos = 0;
+        for n in [1, 3, 7, 8, 13, 64, 5, 128, 2, 17] {
+            let e: alloc::vec::Vec<u128> =
+                crate::repack_with(&data, 8, n, n, options.src_offset(pos)).unwrap();
+            assert_eq!(reader.peek_bits::<u128>(n), Ok(e[0]), "{} {:?}", n, order);
+            assert_eq!(reader.read_bits::<u128>(n), Ok(e[0]), "{} {:?}", n, order);
+            pos += n;
+            assert_eq!(reader.position(), pos);
+            assert_eq!(reader.remaining(), Some(320 - pos));
+        }
+    }
+}
+
+// Выравнивание, пропуск и конец потока.
+#[test]
+fn test2() {
+    let data = [0b_1000_0001u8, 0xFF, 0x0F];
+    let mut reader = BitReader::with_order(&data[..], BitOrder::Lsb0);
+    assert_eq!(reader.read_bool(), Ok(true));
+    assert_eq!(reader.read_bool(), Ok(false));
+    reader.align_to_byte();
+    assert_eq!(reader.position(), 8);
+    reader.align_to_byte();
+    assert_eq!(reader.position(), 8);
+    assert_eq!(reader.read_bits::<u8>(9), Err(ReadError::BitsTooLarge { bits: 9, size: 8 }));
+    assert_eq!(reader.skip(4), Ok(()));
+    assert_eq!(reader.read_bits::<u16>(8), Ok(0xFF));
+
+    let r = reader.read_bits::<u16>(5);
+    assert_eq!(r, Err(ReadError::UnexpectedEnd { position: 20, requested: 5, available: 4 }));
+    assert_eq!(reader.position(), 20);
+    assert_eq!(reader.read_bits::<u16>(4), Ok(0));
+    assert_eq!(reader.read_bits::<u8>(0), Ok(0));
+
+    let mut reader = BitReader::new(&data[..]);
+    let r = reader.skip(30);
+    assert_eq!(r, Err(ReadError::UnexpectedEnd { position: 0, requested: 30, available: 24 }));
+    assert_eq!(reader.position(), 24);
+}
+
+// Чтение из std::io::Read.
+#[test]
+#[cfg(feature = "std")]
+fn test3() {
+    let data = [0xABu8, 0xCD, 0xEF];
+    let mut reader = BitReader::new(IoSource::new(std::io::Cursor::new(data)));
+    assert_eq!(reader.remaining(), None);
+    assert_eq!(reader.read_bits::<u8>(4), Ok(0xA));
+    assert_eq!(reader.read_bits::<u16>(16), Ok(0xBCDE));
+    let r = reader.read_bits::<u8>(8);
+    assert_eq!(r, Err(ReadError::UnexpectedEnd { position: 20, requested: 8, available: 4 }));
+    assert_eq!(reader.read_bits::<u8>(4), Ok(0xF));
+}
+
+// Поле шире 128 бит нельзя прочитать и в тип шире 128 бит.
+#[test]
+fn test4() {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Wide(u128);
+
+    impl Word for Wide {
+        const BITS: usize = 256;
+        const SIGNED: bool = false;
+
+        fn to_raw(self) -> u128 {
+            self.0
+        }
+
+        fn from_raw(raw: u128) -> Self {
+            Wide(raw)
+        }
+    }
+
+    let data = [0xA5u8; 40];
+    let mut reader = BitReader::new(&data[..]);
+    assert_eq!(reader.peek_bits::<Wide>(129), Err(ReadError::BitsTooLarge { bits: 129, size: 128 }));
+    assert_eq!(reader.read_bits::<Wide>(200), Err(ReadError::BitsTooLarge { bits: 200, size: 128 }));
+    assert_eq!(reader.read_bits::<Wide>(128), Ok(Wide(u128::from_be_bytes([0xA5; 16]))));
+    assert_eq!(reader.position(), 128);
+}
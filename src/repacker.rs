@@ -0,0 +1,274 @@
+//! Потоковая упаковка битовой последовательности.
+
+use core::iter;
+use core::marker::PhantomData;
+
+#[cfg(all(test, feature = "alloc"))]
+use alloc::vec::Vec;
+
+use crate::raw::{load_ordered, mask, pad, shl, sign_extend, store_ordered};
+use crate::{check_value, validate_widths, Padding, RepackError, RepackOptions, Word};
+
+/// Потоковый вариант [`repack`](crate::repack): принимает входные эл-ты
+/// порциями произвольной длины и выдает готовые выходные эл-ты по мере
+/// накопления bits_out бит.
+///
+/// Биты, которых пока не хватает на целый выходной эл-т, переносятся между
+/// вызовами [`Repacker::push`]. Неполный хвост выдается [`Repacker::finish`]
+/// согласно выбранной политике [`Padding`].
+///
+/// Результат совпадает с результатом [`repack`](crate::repack) для
+/// объединенного входа.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{Padding, Repacker};
+///     let mut repacker = Repacker::<u16, u8>::new(3, 2).unwrap();
+///     let mut dst = Vec::new();
+///     repacker.push(&[5], &mut dst).unwrap(); // 101
+///     assert_eq!(dst, [0b_10]);
+///     repacker.push(&[5], &mut dst).unwrap(); // 101
+///     assert_eq!(dst, [0b_10, 0b_11, 0b_01]);
+///     repacker.push(&[7], &mut dst).unwrap(); // 111
+///     assert_eq!(dst, [0b_10, 0b_11, 0b_01, 0b_11]);
+///     assert_eq!(repacker.finish(Padding::PadZeros, &mut dst), Ok(1));
+///     assert_eq!(dst, [0b_10, 0b_11, 0b_01, 0b_11, 0b_10]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Repacker<T1, T2> {
+    bits_in: usize,
+    bits_out: usize,
+    options: RepackOptions,
+    // Биты, которых пока не хватает на целый выходной эл-т (меньше bits_out).
+    acc: u128,
+    acc_len: usize,
+    // Общее кол-во принятых бит.
+    total: usize,
+    _marker: PhantomData<fn(&[T1]) -> T2>,
+}
+
+impl<T1: Word, T2: Word> Repacker<T1, T2> {
+    /// Создает упаковщик с параметрами по умолчанию.
+    ///
+    /// # Arguments
+    /// * `bits_in` - кол-во значащих бит (справа) в каждом входном эл-те.
+    /// * `bits_out` - кол-во значащих бит (справа) в каждом выходном эл-те.
+    ///
+    /// # Errors
+    /// * [`RepackError::ZeroWidth`] - bits_in или bits_out равен нулю.
+    /// * [`RepackError::BitsInTooLarge`] - bits_in больше размера T1.
+    /// * [`RepackError::BitsOutTooLarge`] - bits_out больше размера T2.
+    pub fn new(bits_in: usize, bits_out: usize) -> Result<Self, RepackError> {
+        Self::with_options(bits_in, bits_out, RepackOptions::new())
+    }
+
+    /// То же, что и [`Repacker::new`], но с дополнительными параметрами упаковки.
+    ///
+    /// Используются порядки битов и байтов, [`RepackOptions::strict_values`]
+    /// и [`RepackOptions::signed`]. Смещения, [`RepackOptions::strict_length`]
+    /// и [`RepackOptions::padding`] к потоку не применимы и не используются:
+    /// политика дополнения передается в [`Repacker::finish`].
+    pub fn with_options(bits_in: usize, bits_out: usize, options: RepackOptions) -> Result<Self, RepackError> {
+        validate_widths::<T1, T2>(bits_in, bits_out, usize::MAX)?;
+        Ok(Repacker {
+            bits_in,
+            bits_out,
+            options,
+            acc: 0,
+            acc_len: 0,
+            total: 0,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Принимает очередную порцию входных эл-тов и добавляет в dst все
+    /// выходные эл-ты, для которых накопилось достаточно бит.
+    ///
+    /// Возвращает кол-во добавленных эл-тов. При ошибке порция не принимается
+    /// целиком.
+    ///
+    /// # Errors
+    /// * [`RepackError::ValueTooWide`] - во входном эл-те установлены биты выше
+    ///   bits_in (строгий режим). Индекс считается от начала потока.
+    /// * [`RepackError::ValueOverflow`] - входной эл-т не помещается в bits_in бит
+    ///   (знаковый режим).
+    /// * [`RepackError::LengthOverflow`] - общее кол-во принятых бит не помещается в `usize`.
+    pub fn push<E: Extend<T2>>(&mut self, src: &[T1], dst: &mut E) -> Result<usize, RepackError> {
+        let total = src
+            .len()
+            .checked_mul(self.bits_in)
+            .and_then(|n| n.checked_add(self.total))
+            .ok_or(RepackError::LengthOverflow)?;
+        if self.options.get_signed() && T1::SIGNED || self.options.get_strict_values() {
+            let first = self.total / self.bits_in;
+            for (i, &v) in src.iter().enumerate() {
+                check_value(first + i, v, self.bits_in, self.options)?;
+            }
+        }
+
+        let mut count = 0;
+        for &v in src {
+            let v = load_ordered(v, self.bits_in, self.options.get_src_order(), self.options.get_src_byte_order());
+            let mut left = self.bits_in;
+            while left > 0 {
+                let take = left.min(self.bits_out - self.acc_len);
+                left -= take;
+                self.acc = shl(self.acc, take) | ((v >> left) & mask(take));
+                self.acc_len += take;
+                if self.acc_len == self.bits_out {
+                    dst.extend(iter::once(self.word(self.acc)));
+                    self.acc = 0;
+                    self.acc_len = 0;
+                    count += 1;
+                }
+            }
+        }
+        self.total = total;
+        Ok(count)
+    }
+
+    /// Кол-во принятых бит, которые еще не попали в выходные эл-ты.
+    pub fn pending_bits(&self) -> usize {
+        self.acc_len
+    }
+
+    /// Завершает упаковку: выдает в dst неполный последний эл-т согласно
+    /// политике padding.
+    ///
+    /// Возвращает кол-во значащих бит в выданном эл-те
+    /// (0, если неполного эл-та не было или он отброшен).
+    ///
+    /// # Errors
+    /// * [`RepackError::UnalignedBitsLimit`] - остались биты, а padding = [`Padding::Reject`].
+    pub fn finish<E: Extend<T2>>(self, padding: Padding, dst: &mut E) -> Result<usize, RepackError> {
+        if self.acc_len == 0 {
+            return Ok(0);
+        }
+
+        if padding == Padding::Reject {
+            return Err(RepackError::UnalignedBitsLimit {
+                bits_limit: self.total,
+                bits_out: self.bits_out,
+            });
+        }
+
+        let (order, bytes) = (self.options.get_dst_order(), self.options.get_dst_byte_order());
+        match pad(self.acc, self.acc_len, self.bits_out, padding, order, bytes) {
+            Some(seq) => {
+                dst.extend(iter::once(self.word(seq)));
+                Ok(self.acc_len)
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn word(&self, seq: u128) -> T2 {
+        let w: T2 = store_ordered(seq, self.bits_out, self.options.get_dst_order(), self.options.get_dst_byte_order());
+        if self.options.get_signed() && T2::SIGNED && self.bits_out < T2::BITS {
+            return T2::from_raw(sign_extend(w.to_raw(), self.bits_out));
+        }
+        w
+    }
+}
+
+// Результат не зависит от того, как вход разбит на порции.
+#[test]
+#[cfg(feature = "alloc")]
+fn test1() {
+    let src: Vec<u16> = (0..200u16).map(|i| i.wrapping_mul(40503) >> 4).collect();
+    let expected: Vec<u8> = crate::repack(&src, 12, 5, 200 * 12 / 5 * 5).unwrap();
+    for chunk in [1, 2, 3, 7, 64, 200] {
+        let mut repacker = Repacker::<u16, u8>::new(12, 5).unwrap();
+        let mut dst = Vec::new();
+        for part in src.chunks(chunk) {
+            repacker.push(part, &mut dst).unwrap();
+        }
+        assert_eq!(repacker.pending_bits(), 200 * 12 % 5);
+        assert_eq!(repacker.finish(Padding::Truncate, &mut dst), Ok(0));
+        assert_eq!(dst, expected, "chunk = {}", chunk);
+    }
+}
+
+// Политики дополнения последнего эл-та.
+#[test]
+#[cfg(feature = "alloc")]
+fn test2() {
+    let options = RepackOptions::new().dst_order(crate::BitOrder::Lsb0);
+    let src = [0b_1011u8];
+
+    let mut dst = Vec::new();
+    let mut repacker = Repacker::<u8, u8>::with_options(4, 6, options).unwrap();
+    repacker.push(&src, &mut dst).unwrap();
+    assert_eq!(repacker.clone().finish(Padding::PadZeros, &mut dst), Ok(4));
+    assert_eq!(repacker.clone().finish(Padding::PadOnes, &mut dst), Ok(4));
+    assert_eq!(repacker.clone().finish(Padding::Truncate, &mut dst), Ok(0));
+    assert_eq!(dst, [0b_001101, 0b_111101]);
+
+    let r = repacker.finish(Padding::Reject, &mut dst);
+    assert_eq!(r, Err(RepackError::UnalignedBitsLimit { bits_limit: 4, bits_out: 6 }));
+}
+
+// Ширины эл-тов проверяются так же, как в repack.
+#[test]
+fn test3() {
+    let r = Repacker::<u8, u16>::new(9, 16);
+    assert_eq!(r.unwrap_err(), RepackError::BitsInTooLarge { bits_in: 9, size: 8 });
+}
+
+// Порядок байтов эл-тов учитывается так же, как в repack_with.
+#[test]
+#[cfg(feature = "alloc")]
+fn test4() {
+    use crate::{BitOrder, ByteOrder};
+
+    let src: Vec<u32> = (0..50u32).map(|i| i.wrapping_mul(2_654_435_761) >> 12).collect();
+    let orders = [
+        (ByteOrder::Little, ByteOrder::Big),
+        (ByteOrder::Big, ByteOrder::Little),
+        (ByteOrder::Little, ByteOrder::Little),
+    ];
+    for (src_bytes, dst_bytes) in orders {
+        let options = RepackOptions::new()
+            .src_byte_order(src_bytes)
+            .dst_byte_order(dst_bytes)
+            .dst_order(BitOrder::Lsb0)
+            .padding(Padding::PadWith(0x5A5A));
+        let expected: Vec<u16> = crate::repack_with(&src, 20, 13, 50 * 20, options).unwrap();
+        let mut repacker = Repacker::<u32, u16>::with_options(20, 13, options).unwrap();
+        let mut dst = Vec::new();
+        repacker.push(&src, &mut dst).unwrap();
+        repacker.finish(Padding::PadWith(0x5A5A), &mut dst).unwrap();
+        assert_eq!(dst, expected, "{:?} {:?}", src_bytes, dst_bytes);
+    }
+}
+
+// Строгий и знаковый режимы, переполнение счетчика бит.
+#[test]
+#[cfg(feature = "alloc")]
+fn test5() {
+    let options = RepackOptions::new().strict_values(true);
+    let mut repacker = Repacker::<u8, u8>::with_options(4, 8, options).unwrap();
+    let mut dst = Vec::new();
+    assert_eq!(repacker.push(&[1, 2, 3], &mut dst), Ok(1));
+    assert_eq!(repacker.push(&[4, 0x15], &mut dst), Err(RepackError::ValueTooWide { index: 4, value: 0x15, bits_in: 4 }));
+    assert_eq!((dst.as_slice(), repacker.pending_bits()), (&[0x12][..], 4));
+
+    let src = [-3i16, 7, -8, 0, 5];
+    let options = RepackOptions::new().signed(true);
+    let expected: Vec<i8> = crate::repack_with(&src, 4, 6, 18, options).unwrap();
+    let mut repacker = Repacker::<i16, i8>::with_options(4, 6, options).unwrap();
+    let mut dst = Vec::new();
+    repacker.push(&src[..4], &mut dst).unwrap();
+    assert_eq!(repacker.push(&[8], &mut dst), Err(RepackError::ValueOverflow { index: 4, value: 8, bits_in: 4 }));
+    repacker.push(&src[4..], &mut dst).unwrap();
+    assert_eq!(repacker.finish(Padding::Truncate, &mut dst), Ok(0));
+    assert_eq!(dst, expected);
+    assert!(dst.iter().any(|&v| v < 0));
+
+    let mut repacker = Repacker::<u8, u8>::new(8, 8).unwrap();
+    let mut dst = Vec::new();
+    repacker.total = usize::MAX - 8;
+    assert_eq!(repacker.push(&[1], &mut dst), Ok(1));
+    assert_eq!(repacker.push(&[1], &mut dst), Err(RepackError::LengthOverflow));
+}
@@ -0,0 +1,403 @@
+//! Битовые представления срезов целых чисел без копирования.
+
+use core::fmt;
+use core::iter::{Copied, FusedIterator};
+use core::ops::{Bound, RangeBounds};
+
+use crate::raw::{load, mask, store, BitSource};
+use crate::{validate_widths, BitOrder, RepackError, Word};
+
+/// Битовая последовательность, упакованная в срез целых чисел, по bits
+/// значащих бит (справа) в каждом эл-те, как входной срез
+/// [`repack`](crate::repack). Данные не копируются.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::BitSlice;
+///     let data = [5u16, 5]; // [0b_101, 0b_101]
+///     let bits = BitSlice::new(&data, 3).unwrap();
+///     assert_eq!(bits.len(), 6);
+///     assert_eq!(bits.get(1), Some(false));
+///     assert_eq!(bits.count_ones(), 4);
+///     assert_eq!(bits.slice(1..).first_one(), Some(1));
+/// ```
+#[derive(Clone, Copy)]
+pub struct BitSlice<'a, T> {
+    data: &'a [T],
+    bits: usize,
+    order: BitOrder,
+    // Номер первого бита и кол-во бит представления.
+    start: usize,
+    len: usize,
+}
+
+/// Изменяемое битовое представление среза целых чисел (см. [`BitSlice`]).
+///
+/// Изменяются только значащие биты эл-тов, входящие в представление,
+/// остальные биты эл-тов сохраняются.
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::{BitSlice, BitSliceMut};
+///     let mut data = [0u8; 2];
+///     let mut bits = BitSliceMut::new(&mut data, 4).unwrap();
+///     bits.set(0, true);
+///     bits.slice_mut(2..6).copy_from(&BitSlice::new(&[0b_1011u32], 4).unwrap());
+///     assert_eq!(data, [0b_1010, 0b_1100]);
+/// ```
+pub struct BitSliceMut<'a, T> {
+    data: &'a mut [T],
+    bits: usize,
+    order: BitOrder,
+    start: usize,
+    len: usize,
+}
+
+/// Итератор по битам [`BitSlice`].
+#[derive(Clone)]
+pub struct BitIter<'a, T> {
+    slice: BitSlice<'a, T>,
+    index: usize,
+}
+
+impl<'a, T: Word> BitSlice<'a, T> {
+    /// Представление всех значащих бит data, начиная со старшего в каждом эл-те.
+    ///
+    /// # Errors
+    /// * [`RepackError::ZeroWidth`] - bits равен нулю.
+    /// * [`RepackError::BitsInTooLarge`] - bits больше размера T.
+    pub fn new(data: &'a [T], bits: usize) -> Result<Self, RepackError> {
+        Self::with_order(data, bits, BitOrder::Msb0)
+    }
+
+    /// То же, что и [`BitSlice::new`], но с заданным порядком битов в эл-тах.
+    pub fn with_order(data: &'a [T], bits: usize, order: BitOrder) -> Result<Self, RepackError> {
+        validate_widths::<T, T>(bits, bits, usize::MAX)?;
+        Ok(BitSlice {
+            data,
+            bits,
+            order,
+            start: 0,
+            len: data.len() * bits,
+        })
+    }
+
+    /// Кол-во бит.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Пусто ли представление.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Кол-во значащих бит в каждом эл-те.
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    /// Порядок битов в эл-тах.
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    /// Бит с номером i или None, если i за пределами представления.
+    pub fn get(&self, i: usize) -> Option<bool> {
+        if i < self.len {
+            Some(get_bit(self.data, self.bits, self.order, self.start + i))
+        } else {
+            None
+        }
+    }
+
+    /// Часть представления.
+    ///
+    /// # Panics
+    /// Если диапазон выходит за пределы представления.
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> BitSlice<'a, T> {
+        let (start, end) = bounds(range, self.len);
+        BitSlice {
+            start: self.start + start,
+            len: end - start,
+            ..*self
+        }
+    }
+
+    /// Итератор по битам.
+    pub fn iter(&self) -> BitIter<'a, T> {
+        BitIter { slice: *self, index: 0 }
+    }
+
+    /// Кол-во единичных бит.
+    pub fn count_ones(&self) -> usize {
+        let mut source = self.source();
+        let mut count = 0;
+        let mut left = self.len;
+        while left > 0 {
+            let n = left.min(128);
+            count += source.read(n).0.count_ones() as usize;
+            left -= n;
+        }
+        count
+    }
+
+    /// Номер первого единичного бита или None, если их нет.
+    pub fn first_one(&self) -> Option<usize> {
+        let mut source = self.source();
+        let mut pos = 0;
+        while pos < self.len {
+            let n = (self.len - pos).min(128);
+            let chunk = source.read(n).0;
+            if chunk != 0 {
+                return Some(pos + n - (128 - chunk.leading_zeros() as usize));
+            }
+            pos += n;
+        }
+        None
+    }
+
+    /// Копия битов в [`BitVec`](crate::BitVec).
+    #[cfg(feature = "alloc")]
+    pub fn to_bitvec(&self) -> crate::BitVec {
+        self.iter().collect()
+    }
+
+    fn source(&self) -> BitSource<Copied<core::slice::Iter<'a, T>>> {
+        let index = self.start / self.bits;
+        let mut source = BitSource::new(self.data[index..].iter().copied(), self.bits, self.order);
+        source.read(self.start % self.bits);
+        source
+    }
+}
+
+impl<'a, T: Word> BitSliceMut<'a, T> {
+    /// Изменяемое представление всех значащих бит data, начиная со старшего
+    /// в каждом эл-те.
+    ///
+    /// # Errors
+    /// * [`RepackError::ZeroWidth`] - bits равен нулю.
+    /// * [`RepackError::BitsInTooLarge`] - bits больше размера T.
+    pub fn new(data: &'a mut [T], bits: usize) -> Result<Self, RepackError> {
+        Self::with_order(data, bits, BitOrder::Msb0)
+    }
+
+    /// То же, что и [`BitSliceMut::new`], но с заданным порядком битов в эл-тах.
+    pub fn with_order(data: &'a mut [T], bits: usize, order: BitOrder) -> Result<Self, RepackError> {
+        validate_widths::<T, T>(bits, bits, usize::MAX)?;
+        let len = data.len() * bits;
+        Ok(BitSliceMut {
+            data,
+            bits,
+            order,
+            start: 0,
+            len,
+        })
+    }
+
+    // Первые len бит представления.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn prefix(mut self, len: usize) -> Self {
+        assert!(len <= self.len);
+        self.len = len;
+        self
+    }
+
+    /// Неизменяемое представление тех же бит.
+    pub fn as_bit_slice(&self) -> BitSlice<'_, T> {
+        BitSlice {
+            data: self.data,
+            bits: self.bits,
+            order: self.order,
+            start: self.start,
+            len: self.len,
+        }
+    }
+
+    /// Кол-во бит.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Пусто ли представление.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Бит с номером i или None, если i за пределами представления.
+    pub fn get(&self, i: usize) -> Option<bool> {
+        self.as_bit_slice().get(i)
+    }
+
+    /// Устанавливает бит с номером i.
+    ///
+    /// # Panics
+    /// Если i за пределами представления.
+    pub fn set(&mut self, i: usize, bit: bool) {
+        assert!(i < self.len, "index {} out of range for bit slice of length {}", i, self.len);
+        write_bits(self.data, self.bits, self.order, self.start + i, bit as u128, 1);
+    }
+
+    /// Изменяемая часть представления.
+    ///
+    /// # Panics
+    /// Если диапазон выходит за пределы представления.
+    pub fn slice_mut<R: RangeBounds<usize>>(&mut self, range: R) -> BitSliceMut<'_, T> {
+        let (start, end) = bounds(range, self.len);
+        BitSliceMut {
+            data: self.data,
+            bits: self.bits,
+            order: self.order,
+            start: self.start + start,
+            len: end - start,
+        }
+    }
+
+    /// Копирует биты из представления с другим типом эл-тов, bits или порядком битов.
+    ///
+    /// # Panics
+    /// Если длины представлений различаются.
+    pub fn copy_from<U: Word>(&mut self, src: &BitSlice<'_, U>) {
+        assert_eq!(self.len, src.len, "bit slices have different lengths");
+        let mut source = src.source();
+        let mut pos = self.start;
+        let end = self.start + self.len;
+        while pos < end {
+            // Не больше, чем осталось в текущем эл-те.
+            let n = (self.bits - pos % self.bits).min(end - pos);
+            write_bits(self.data, self.bits, self.order, pos, source.read(n).0, n);
+            pos += n;
+        }
+    }
+}
+
+impl<T: Word> Iterator for BitIter<'_, T> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        let bit = self.slice.get(self.index)?;
+        self.index += 1;
+        Some(bit)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.slice.len - self.index;
+        (n, Some(n))
+    }
+}
+
+impl<T: Word> ExactSizeIterator for BitIter<'_, T> {}
+
+impl<T: Word> FusedIterator for BitIter<'_, T> {}
+
+impl<'a, T: Word> IntoIterator for BitSlice<'a, T> {
+    type Item = bool;
+    type IntoIter = BitIter<'a, T>;
+
+    fn into_iter(self) -> BitIter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: Word, U: Word> PartialEq<BitSlice<'_, U>> for BitSlice<'_, T> {
+    fn eq(&self, other: &BitSlice<'_, U>) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Word> fmt::Debug for BitSlice<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("0b")?;
+        for bit in self.iter() {
+            f.write_str(if bit { "1" } else { "0" })?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Word> fmt::Debug for BitSliceMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.as_bit_slice(), f)
+    }
+}
+
+// Начало и конец диапазона внутри 0..len.
+fn bounds<R: RangeBounds<usize>>(range: R, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&i) => i,
+        Bound::Excluded(&i) => i + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&i) => i + 1,
+        Bound::Excluded(&i) => i,
+        Bound::Unbounded => len,
+    };
+    assert!(start <= end && end <= len, "range {}..{} out of range for length {}", start, end, len);
+    (start, end)
+}
+
+// Бит с номером pos последовательности.
+fn get_bit<T: Word>(data: &[T], bits: usize, order: BitOrder, pos: usize) -> bool {
+    let b = pos % bits;
+    let shift = match order {
+        BitOrder::Msb0 => bits - b - 1,
+        BitOrder::Lsb0 => b,
+    };
+    (data[pos / bits].to_raw() >> shift) & 1 != 0
+}
+
+// Записывает n бит seq в последовательность, начиная с бита pos.
+// Все n бит должны находиться в одном эл-те, остальные биты эл-та сохраняются.
+fn write_bits<T: Word>(data: &mut [T], bits: usize, order: BitOrder, pos: usize, seq: u128, n: usize) {
+    let k = pos / bits;
+    let shift = bits - pos % bits - n;
+    let m = mask(n) << shift;
+    let old = data[k].to_raw();
+    let cur = (load(data[k], bits, order) & !m) | ((seq << shift) & m);
+    let new = store::<u128>(cur, bits, order);
+    data[k] = T::from_raw((old & !mask(bits)) | new);
+}
+
+// Представления читаются так же, как repack читает входной срез.
+#[test]
+#[cfg(feature = "alloc")]
+fn test1() {
+    let data = [0x123u16, 0x456, 0x789];
+    let bits = BitSlice::new(&data, 12).unwrap();
+    let expected: alloc::vec::Vec<u8> = crate::repack(&data, 12, 1, 36).unwrap();
+    assert!(bits.iter().map(u8::from).eq(expected.iter().copied()));
+    assert_eq!(bits.iter().len(), 36);
+    assert_eq!(bits.count_ones(), expected.iter().filter(|&&b| b == 1).count());
+    assert_eq!(bits.slice(4..16), BitSlice::new(&[0x234u16], 12).unwrap());
+    assert_eq!(bits.slice(8..).first_one(), Some(2));
+    assert_eq!(bits.slice(12..13).first_one(), None);
+    assert_eq!(bits.slice(5..25).to_bitvec().len(), 20);
+}
+
+// count_ones и first_one на длинных представлениях.
+#[test]
+fn test2() {
+    let data = [0u64, 0, 0, 1 << 40];
+    let bits = BitSlice::with_order(&data, 64, BitOrder::Lsb0).unwrap();
+    assert_eq!(bits.first_one(), Some(3 * 64 + 40));
+    assert_eq!(bits.count_ones(), 1);
+    assert_eq!(bits.slice(..3 * 64 + 40).first_one(), None);
+    assert_eq!(bits.get(3 * 64 + 40), Some(true));
+}
+
+// Копирование между представлениями с разными типами, ширинами и порядками битов
+// не трогает соседние и незначащие биты.
+#[test]
+fn test3() {
+    let src = [0b_101u8, 0b_110, 0b_011];
+    let src = BitSlice::with_order(&src, 3, BitOrder::Lsb0).unwrap(); // 101 011 110
+    let mut data = [0xF000u16, 0xF000];
+    let mut dst = BitSliceMut::new(&mut data, 8).unwrap();
+    dst.slice_mut(3..12).copy_from(&src);
+    assert_eq!(dst.as_bit_slice().slice(3..12), src);
+    dst.set(0, true);
+    assert_eq!(data, [0xF000 | 0b_1001_0101, 0xF000 | 0b_1110_0000]);
+}
@@ -0,0 +1,77 @@
+//! Целочисленные типы, которые могут быть эл-тами упакованных срезов.
+
+/// Целое число фиксированного размера, используемое как эл-т входного или
+/// выходного среза.
+///
+/// Реализован для всех примитивных целых типов, от `u8`/`i8` до `u128`/`i128`.
+/// Все операции над битами выполняются над 128-битным двоичным представлением
+/// эл-та, поэтому для этих типов преобразования не могут завершиться ошибкой.
+///
+/// Другие целые типы (обертки над примитивными, типы нестандартной ширины)
+/// реализуют трейт сами. Типы шире 128 бит допустимы, но использовать в них
+/// можно не более 128 значащих бит: иначе упаковка возвращает
+/// [`RepackError::WidthTooLarge`](crate::RepackError::WidthTooLarge). Тип выходного
+/// эл-та, [`Word::from_raw`] которого теряет младший бит, отклоняется с
+/// [`RepackError::UnsupportedWord`](crate::RepackError::UnsupportedWord).
+///
+/// # Examples
+///
+/// ```
+///     use bits_rs::Word;
+///
+///     // 12-битный отсчет АЦП.
+///     #[derive(Debug, Clone, Copy, PartialEq)]
+///     struct Sample(u16);
+///
+///     impl Word for Sample {
+///         const BITS: usize = 12;
+///         const SIGNED: bool = false;
+///
+///         fn to_raw(self) -> u128 {
+///             self.0 as u128
+///         }
+///
+///         fn from_raw(raw: u128) -> Self {
+///             Sample(raw as u16 & 0xFFF)
+///         }
+///     }
+///
+///     let mut dst = [Sample(0); 2];
+///     bits_rs::repack_into(&[0xABu8, 0xCD, 0xEF], 8, &mut dst, 12, 24).unwrap();
+///     assert_eq!(dst, [Sample(0xABC), Sample(0xDEF)]);
+/// ```
+pub trait Word: Copy {
+    /// Размер типа в битах.
+    const BITS: usize;
+
+    /// Является ли тип знаковым.
+    const SIGNED: bool;
+
+    /// Двоичное представление эл-та, расширенное до 128 бит
+    /// (для знаковых типов - с расширением знака).
+    fn to_raw(self) -> u128;
+
+    /// Эл-т из младших [`Word::BITS`] бит двоичного представления.
+    fn from_raw(raw: u128) -> Self;
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {$(
+        impl Word for $t {
+            const BITS: usize = <$t>::BITS as usize;
+            const SIGNED: bool = <$t>::MIN != 0;
+
+            #[inline]
+            fn to_raw(self) -> u128 {
+                self as u128
+            }
+
+            #[inline]
+            fn from_raw(raw: u128) -> Self {
+                raw as $t
+            }
+        }
+    )*};
+}
+
+impl_word!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
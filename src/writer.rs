@@ -0,0 +1,339 @@
+//! Запись полей переменной ширины в битовый поток.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::raw::{mask, pad, reverse, shr, store, value_of};
+use crate::{BitOrder, ByteOrder, Padding, Word, WriteError};
+
+/// Приемник байтов для [`BitWriter`].
+///
+/// Реализован для `Vec<u8>` (при включенном `alloc`), [`IoSink`]
+/// (при включенном `std`) и изменяемых ссылок на приемники.
+pub trait ByteSink {
+    /// Записывает байт.
+    fn write_byte(&mut self, b: u8) -> Result<(), WriteError>;
+
+    /// Записывает байты.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), WriteError> {
+        for &b in bytes {
+            self.write_byte(b)?;
+        }
+        Ok(())
+    }
+
+    /// Сбрасывает буферы приемника.
+    fn flush(&mut self) -> Result<(), WriteError> {
+        Ok(())
+    }
+}
+
+impl<S: ByteSink + ?Sized> ByteSink for &mut S {
+    fn write_byte(&mut self, b: u8) -> Result<(), WriteError> {
+        (**self).write_byte(b)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), WriteError> {
+        (**self).write_bytes(bytes)
+    }
+
+    fn flush(&mut self) -> Result<(), WriteError> {
+        (**self).flush()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ByteSink for Vec<u8> {
+    fn write_byte(&mut self, b: u8) -> Result<(), WriteError> {
+        self.push(b);
+        Ok(())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), WriteError> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// Приемник байтов для [`std::io::Write`].
+///
+/// Целые байты передаются в поток сразу, по одному, поэтому небуферизованные
+/// потоки (файлы, сокеты) лучше обернуть в [`std::io::BufWriter`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct IoSink<W> {
+    inner: W,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> IoSink<W> {
+    /// Приемник, пишущий байты в inner.
+    pub fn new(inner: W) -> Self {
+        IoSink { inner }
+    }
+
+    /// Исходный поток.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> ByteSink for IoSink<W> {
+    fn write_byte(&mut self, b: u8) -> Result<(), WriteError> {
+        self.write_bytes(&[b])
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), WriteError> {
+        self.inner.write_all(bytes).map_err(|e| WriteError::Io(e.kind()))
+    }
+
+    fn flush(&mut self) -> Result<(), WriteError> {
+        self.inner.flush().map_err(|e| WriteError::Io(e.kind()))
+    }
+}
+
+/// Записывает в поток байтов поля произвольной ширины, одно за другим.
+///
+/// Порядок битов такой же, как у [`BitReader`](crate::BitReader): поток,
+/// записанный в некотором порядке, читается в том же порядке.
+///
+/// Целые байты сразу передаются в приемник, неполный последний байт
+/// выдается [`BitWriter::finish`] согласно выбранной политике [`Padding`].
+/// Без вызова finish неполный байт теряется.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "alloc")] {
+///     use bits_rs::{BitWriter, Padding};
+///     let mut writer = BitWriter::new(Vec::new());
+///     writer.write_bits(5u8, 3).unwrap();
+///     writer.write_bits(2u16, 13).unwrap();
+///     writer.write_bool(true).unwrap();
+///     assert_eq!(writer.position(), 17);
+///     assert_eq!(writer.finish(Padding::PadZeros), Ok(1));
+///     assert_eq!(writer.into_inner(), [0b_1010_0000, 0b_0000_0010, 0b_1000_0000]);
+/// # }
+/// ```
+///
+/// ```
+/// # #[cfg(feature = "alloc")] {
+///     use bits_rs::{BitWriter, WriteError};
+///     let mut writer = BitWriter::new(Vec::new());
+///     let r = writer.write_bits(8u8, 3);
+///     assert_eq!(r, Err(WriteError::ValueTooWide { value: 8, bits: 3 }));
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BitWriter<S> {
+    sink: S,
+    order: BitOrder,
+    // Биты неполного байта в порядке потока (меньше 8).
+    cur: u128,
+    cur_len: usize,
+    // Кол-во записанных бит.
+    pos: usize,
+}
+
+impl<S: ByteSink> BitWriter<S> {
+    /// Писатель в приемник sink в порядке [`BitOrder::Msb0`].
+    pub fn new(sink: S) -> Self {
+        Self::with_order(sink, BitOrder::Msb0)
+    }
+
+    /// То же, что и [`BitWriter::new`], но с заданным порядком битов.
+    pub fn with_order(sink: S, order: BitOrder) -> Self {
+        BitWriter {
+            sink,
+            order,
+            cur: 0,
+            cur_len: 0,
+            pos: 0,
+        }
+    }
+
+    /// Порядок битов.
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    /// Кол-во записанных бит.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Приемник. Неполный последний байт в него еще не записан.
+    pub fn get_ref(&self) -> &S {
+        &self.sink
+    }
+
+    /// Приемник. Неполный последний байт, если он есть, теряется.
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+
+    /// Записывает младшие n бит value.
+    ///
+    /// # Errors
+    /// * [`WriteError::BitsTooLarge`] - n больше размера T или 128 бит
+    ///   (для пользовательских реализаций [`Word`] шире 128 бит).
+    /// * [`WriteError::ValueTooWide`] - в value установлены биты выше n
+    ///   (в том числе у отрицательных значений при n меньше размера T).
+    pub fn write_bits<T: Word>(&mut self, value: T, n: usize) -> Result<(), WriteError> {
+        if n > T::BITS.min(128) {
+            return Err(WriteError::BitsTooLarge { bits: n, size: T::BITS.min(128) });
+        }
+        if n < T::BITS && shr(value.to_raw() & mask(T::BITS), n) != 0 {
+            return Err(WriteError::ValueTooWide { value: value_of(value), bits: n });
+        }
+        if n == 0 {
+            return Ok(());
+        }
+
+        // Биты в порядке потока, первый - старший.
+        let seq = match self.order {
+            BitOrder::Msb0 => value.to_raw() & mask(n),
+            BitOrder::Lsb0 => reverse(value.to_raw(), n),
+        };
+        let mut left = n;
+        while left > 0 {
+            let take = left.min(8 - self.cur_len);
+            left -= take;
+            self.cur = (self.cur << take) | ((seq >> left) & mask(take));
+            self.cur_len += take;
+            self.pos += take;
+            if self.cur_len == 8 {
+                self.sink.write_byte(store(self.cur, 8, self.order))?;
+                self.cur = 0;
+                self.cur_len = 0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Записывает один бит.
+    pub fn write_bool(&mut self, bit: bool) -> Result<(), WriteError> {
+        self.write_bits(bit as u8, 1)
+    }
+
+    /// Записывает байты, по 8 бит каждый.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), WriteError> {
+        if self.cur_len == 0 {
+            self.sink.write_bytes(bytes)?;
+            self.pos += bytes.len() * 8;
+            return Ok(());
+        }
+        for &b in bytes {
+            self.write_bits(b, 8)?;
+        }
+        Ok(())
+    }
+
+    /// Завершает запись: выдает в приемник неполный последний байт согласно
+    /// политике padding и сбрасывает буферы приемника.
+    ///
+    /// Возвращает кол-во значащих бит в выданном байте
+    /// (0, если неполного байта не было или он отброшен).
+    ///
+    /// # Errors
+    /// * [`WriteError::Unaligned`] - есть неполный байт, а padding = [`Padding::Reject`].
+    pub fn finish(&mut self, padding: Padding) -> Result<usize, WriteError> {
+        let len = self.cur_len;
+        if len > 0 {
+            if padding == Padding::Reject {
+                return Err(WriteError::Unaligned { position: self.pos });
+            }
+            if let Some(seq) = pad(self.cur, len, 8, padding, self.order, ByteOrder::Big) {
+                self.sink.write_byte(store(seq, 8, self.order))?;
+                self.pos += 8 - len;
+            } else {
+                self.pos -= len;
+            }
+            self.cur = 0;
+            self.cur_len = 0;
+        }
+        self.sink.flush()?;
+        Ok(if padding == Padding::Truncate { 0 } else { len })
+    }
+}
+
+// Записанное читается BitReader в том же порядке.
+#[test]
+#[cfg(feature = "alloc")]
+fn test1() {
+    let fields = [(1, 1u128), (3, 5), (7, 100), (8, 0xA5), (13, 0x1234), (64, 1 << 63), (5, 0), (128, u128::MAX - 7)];
+    for order in [BitOrder::Msb0, BitOrder::Lsb0] {
+        let mut writer = BitWriter::with_order(Vec::new(), order);
+        for &(n, v) in &fields {
+            writer.write_bits(v, n).unwrap();
+        }
+        writer.write_bytes(&[0xC3, 0x3C]).unwrap();
+        assert_eq!(writer.finish(Padding::PadOnes), Ok(5));
+        let bytes = writer.into_inner();
+
+        let mut reader = crate::BitReader::with_order(&bytes[..], order);
+        for &(n, v) in &fields {
+            assert_eq!(reader.read_bits::<u128>(n), Ok(v), "{} {:?}", n, order);
+        }
+        assert_eq!(reader.read_bits::<u16>(16), Ok(if order == BitOrder::Msb0 { 0xC33C } else { 0x3CC3 }));
+        assert_eq!(reader.read_bits::<u8>(3), Ok(0b_111));
+    }
+}
+
+// Политики дополнения, проверки значений и выровненная запись байтов.
+#[test]
+#[cfg(feature = "alloc")]
+fn test2() {
+    let mut bytes = Vec::new();
+    let mut writer = BitWriter::with_order(&mut bytes, BitOrder::Lsb0);
+    writer.write_bits(0b_101u8, 3).unwrap();
+    assert_eq!(writer.finish(Padding::Reject), Err(WriteError::Unaligned { position: 3 }));
+    assert_eq!(writer.write_bits(-1i8, 4), Err(WriteError::ValueTooWide { value: -1, bits: 4 }));
+    assert_eq!(writer.write_bits(1u8, 9), Err(WriteError::BitsTooLarge { bits: 9, size: 8 }));
+    assert_eq!(writer.finish(Padding::PadWith(0b_0101_0000)), Ok(3));
+    writer.write_bytes(&[1, 2]).unwrap();
+    writer.write_bits(-1i8, 8).unwrap();
+    writer.write_bool(true).unwrap();
+    assert_eq!(writer.finish(Padding::Truncate), Ok(0));
+    assert_eq!(writer.position(), 32);
+    assert_eq!(bytes, [0b_0101_0101, 1, 2, 0xFF]);
+}
+
+// Запись в std::io::Write.
+#[test]
+#[cfg(feature = "std")]
+fn test3() {
+    let mut writer = BitWriter::new(IoSink::new(std::io::Cursor::new(Vec::new())));
+    writer.write_bits(0xABCu16, 12).unwrap();
+    writer.write_bytes(b"x").unwrap();
+    assert_eq!(writer.finish(Padding::PadZeros), Ok(4));
+    assert_eq!(writer.into_inner().into_inner().into_inner(), [0xAB, 0xC7, 0x80]);
+}
+
+// Поле шире 128 бит нельзя записать и из типа шире 128 бит.
+#[test]
+#[cfg(feature = "alloc")]
+fn test4() {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Wide(u128);
+
+    impl Word for Wide {
+        const BITS: usize = 256;
+        const SIGNED: bool = false;
+
+        fn to_raw(self) -> u128 {
+            self.0
+        }
+
+        fn from_raw(raw: u128) -> Self {
+            Wide(raw)
+        }
+    }
+
+    let mut writer = BitWriter::new(Vec::new());
+    assert_eq!(writer.write_bits(Wide(1), 129), Err(WriteError::BitsTooLarge { bits: 129, size: 128 }));
+    assert_eq!(writer.write_bits(Wide(u128::MAX), 128), Ok(()));
+    assert_eq!(writer.position(), 128);
+    assert_eq!(writer.into_inner(), [0xFF; 16]);
+}